@@ -0,0 +1,83 @@
+//! Times line edits near the top of ever larger files, calling the buffer
+//! directly so that drawing the screen isn't part of the measurement. The
+//! rope keeps each edit O(log n), so the time per edit should stay flat as
+//! the file grows by orders of magnitude.
+//!
+//! Run with `cargo bench`.
+#![warn(clippy::all, clippy::pedantic)]
+
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use text_editor::editor::Buffer;
+
+const SIZES: [usize; 4] = [1_000, 10_000, 100_000, 1_000_000];
+const EDITS: u32 = 400;
+
+/// The row of the first edit, far enough down that a join has a line above
+/// it.
+const ROW: usize = 2;
+
+/// Makes the edit with the given number, starting from 0.
+type Edit = fn(&mut Buffer, usize);
+
+const EDITS_TIMED: [(&str, Edit); 3] = [
+    ("insert_line", insert_line),
+    ("delete_line", delete_line),
+    ("join_line", join_line),
+];
+
+fn insert_line(buffer: &mut Buffer, _edit: usize) {
+    buffer
+        .insert_line(ROW, String::from("inserted"))
+        .expect("row should exist");
+}
+
+fn delete_line(buffer: &mut Buffer, _edit: usize) {
+    buffer.delete_line(ROW).expect("row should exist");
+}
+
+/// Joins each line onto a different line above it, so no line grows longer
+/// with every edit.
+fn join_line(buffer: &mut Buffer, edit: usize) {
+    let row = ROW + edit;
+    buffer
+        .move_line_contents_up_one_row(row)
+        .expect("row should exist");
+    buffer.delete_line(row).expect("row should exist");
+}
+
+fn file_with_lines(lines: usize) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("text-editor-bench-{lines}.txt"));
+    let mut text = String::new();
+    for line in 0..lines {
+        let _ = writeln!(text, "line {line}");
+    }
+    std::fs::write(&path, text).expect("bench file should be writable");
+    path
+}
+
+fn time_edits(path: &Path, edit: Edit) -> Duration {
+    let mut buffer = Buffer::new();
+    buffer
+        .read_file(path.to_str())
+        .expect("bench file should be readable");
+    let start = Instant::now();
+    for number in 0..EDITS {
+        edit(&mut buffer, number as usize);
+    }
+    start.elapsed()
+}
+
+fn main() {
+    println!("{:<12} {:>10} {:>14}", "edit", "lines", "per edit");
+    for lines in SIZES {
+        let path = file_with_lines(lines);
+        for (name, edit) in EDITS_TIMED {
+            let elapsed = time_edits(&path, edit);
+            println!("{name:<12} {lines:>10} {:>12.2?}", elapsed / EDITS);
+        }
+        std::fs::remove_file(&path).expect("bench file should be removable");
+    }
+}
@@ -1,39 +1,132 @@
-use buffer::Buffer;
+use std::path::Path;
+
+pub use backend::{Backend, CrosstermBackend, MemoryBackend};
+pub use buffer::Buffer;
+use buffer_list::BufferList;
 use controller::Controller;
+pub use frame::Style;
+use keymap::MapMode;
+use options::Value;
 use terminal::Terminal;
+use window_list::WindowList;
 
+mod backend;
 mod buffer;
+mod buffer_list;
+mod command;
+mod config;
 mod controller;
+mod display;
+mod frame;
+mod gutter;
+mod highlight;
+mod history;
+mod jump_list;
+mod keymap;
+mod keys;
+mod motion;
+mod operator;
+mod options;
+mod register;
+mod repeat;
 mod screen;
+mod selection;
 mod terminal;
+mod window_list;
 
 type IOResult = Result<(), std::io::Error>;
 
-pub struct Editor<'a> {
-    buffer: Buffer<'a>,
+pub struct Editor {
+    buffers: BufferList,
+    windows: WindowList,
     terminal: Terminal,
     controller: Controller,
 }
 
-impl<'a> Default for Editor<'a> {
+impl Default for Editor {
     fn default() -> Self {
+        let buffers = BufferList::new();
         Editor {
-            buffer: Buffer::new(),
+            windows: WindowList::new(buffers.number(buffers.current_index())),
+            buffers,
             terminal: Terminal::new(),
             controller: Controller::new(),
         }
     }
 }
 
-impl<'a> Editor<'a> {
-    pub fn new(file_path: Option<&'a String>) -> Self {
-        let mut editor = Editor::default();
-        if let Err(err) = editor.buffer.read_file(file_path) {
+impl Editor {
+    /// # Panics
+    ///
+    /// Panics if `file_path` exists but can't be read.
+    #[must_use]
+    pub fn new(file_path: Option<&str>) -> Self {
+        let mut editor = Editor::with_backend(file_path, Box::new(CrosstermBackend::new()));
+        if let Some(path) = config::path() {
+            editor.load_config(&path);
+        }
+        editor
+    }
+
+    /// # Panics
+    ///
+    /// Panics if `file_path` exists but can't be read.
+    #[must_use]
+    pub fn with_backend(file_path: Option<&str>, backend: Box<dyn Backend>) -> Self {
+        let mut editor = Editor {
+            terminal: Terminal::with_backend(backend),
+            ..Editor::default()
+        };
+        if let Err(err) = editor.buffers.current_mut().read_file(file_path) {
             panic!("{err:#?}")
         }
         editor
     }
 
+    /// Applies the options and key mappings in the config file at `path`,
+    /// showing the first error in it, if any, once the editor starts.
+    /// Options are applied first, so `leader` holds for every mapping.
+    pub fn load_config(&mut self, path: &Path) {
+        let mut entries = match config::read(path) {
+            Ok(entries) => entries,
+            Err(message) => {
+                self.controller
+                    .set_message(format!("{}: {message}", path.display()));
+                return;
+            }
+        };
+        entries.sort_by_key(|entry| !matches!(entry.table.as_str(), "" | "options"));
+        let mut first_error = None;
+        for entry in entries {
+            let result = match (entry.table.as_str(), entry.value) {
+                ("" | "options", value) => self.terminal.set_option(&entry.key, value),
+                (table, Value::Text(action)) if table.starts_with("keymap") => {
+                    match MapMode::from_table(table) {
+                        Some(modes) => {
+                            self.controller
+                                .map(&self.terminal, &modes, &entry.key, &action);
+                            Ok(())
+                        }
+                        None => Err(format!("Unknown table: {table}")),
+                    }
+                }
+                (table, _) if table.starts_with("keymap") => {
+                    Err(format!("Invalid mapping for {}", entry.key))
+                }
+                (table, _) => Err(format!("Unknown table: {table}")),
+            };
+            if let Err(message) = result {
+                first_error.get_or_insert(format!("{}:{}: {message}", path.display(), entry.line));
+            }
+        }
+        if let Some(message) = first_error {
+            self.controller.set_message(message);
+        }
+    }
+
+    /// # Panics
+    ///
+    /// Panics if the terminal can't be read from or written to.
     pub fn run(&mut self) {
         if let Err(err) = self.repl() {
             panic!("{err:#?}")
@@ -41,11 +134,12 @@ impl<'a> Editor<'a> {
     }
 
     fn repl(&mut self) -> IOResult {
-        Terminal::init()?;
-        self.controller.init(&self.buffer, &mut self.terminal)?;
+        self.terminal.init()?;
+        self.controller
+            .init(&self.buffers, &self.windows, &mut self.terminal)?;
         self.controller
-            .handle_input(&mut self.buffer, &mut self.terminal)?;
-        Terminal::clean_up()?;
+            .handle_input(&mut self.buffers, &mut self.windows, &mut self.terminal)?;
+        self.terminal.clean_up()?;
         Ok(())
     }
 }
@@ -0,0 +1,38 @@
+use std::time::Duration;
+
+use crossterm::cursor::SetCursorStyle;
+use crossterm::event::Event;
+use crossterm::terminal::ClearType;
+
+pub use crossterm_backend::CrosstermBackend;
+pub use memory_backend::MemoryBackend;
+
+use super::frame::Style;
+use super::IOResult;
+
+mod crossterm_backend;
+mod memory_backend;
+
+/// The terminal operations the editor needs, so it can draw to something
+/// other than a real TTY. Every method fails only when the underlying
+/// terminal can't be read from or written to.
+#[allow(clippy::missing_errors_doc)]
+pub trait Backend {
+    fn init(&mut self) -> IOResult;
+    fn clean_up(&mut self) -> IOResult;
+    fn size(&self) -> Result<(usize, usize), std::io::Error>;
+    fn move_cursor_to(&mut self, x: usize, y: usize) -> IOResult;
+    fn hide_cursor(&mut self) -> IOResult;
+    fn show_cursor(&mut self) -> IOResult;
+    fn set_cursor_style(&mut self, style: SetCursorStyle) -> IOResult;
+    fn clear(&mut self, clear_type: ClearType) -> IOResult;
+    fn set_style(&mut self, style: Style) -> IOResult;
+    fn write(&mut self, text: &str) -> IOResult;
+    fn flush(&mut self) -> IOResult;
+    /// Copies `text` to the system clipboard of the terminal's host.
+    fn set_clipboard(&mut self, text: &str) -> IOResult;
+    /// Returns `None` once there are no more events to read.
+    fn read_event(&mut self) -> Result<Option<Event>, std::io::Error>;
+    /// Waits up to `timeout` for an event, returning whether one came.
+    fn poll_event(&mut self, timeout: Duration) -> Result<bool, std::io::Error>;
+}
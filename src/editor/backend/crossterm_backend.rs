@@ -0,0 +1,132 @@
+use std::io::{stdout, Write};
+use std::time::Duration;
+
+use crossterm::cursor::{Hide, MoveTo, SetCursorStyle, Show};
+use crossterm::event::{poll, read, Event};
+use crossterm::style::{
+    Attribute, ResetColor, SetAttribute, SetAttributes, SetBackgroundColor, SetForegroundColor,
+};
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, size, Clear, ClearType, DisableLineWrap, EnableLineWrap,
+};
+use crossterm::{execute, queue};
+
+use super::super::frame::Style;
+use super::super::IOResult;
+use super::Backend;
+
+#[derive(Default)]
+pub struct CrosstermBackend;
+
+impl CrosstermBackend {
+    #[must_use]
+    pub fn new() -> Self {
+        CrosstermBackend
+    }
+}
+
+impl Backend for CrosstermBackend {
+    fn init(&mut self) -> IOResult {
+        enable_raw_mode()?;
+        queue!(stdout(), Clear(ClearType::All), DisableLineWrap)?;
+        stdout().flush()?;
+        Ok(())
+    }
+
+    fn clean_up(&mut self) -> IOResult {
+        execute!(stdout(), EnableLineWrap)?;
+        disable_raw_mode()?;
+        Ok(())
+    }
+
+    fn size(&self) -> Result<(usize, usize), std::io::Error> {
+        let (x, y) = size()?;
+        Ok((x as usize, y as usize))
+    }
+
+    fn move_cursor_to(&mut self, x: usize, y: usize) -> IOResult {
+        queue!(
+            stdout(),
+            MoveTo(
+                u16::try_from(x).unwrap_or(u16::MAX),
+                u16::try_from(y).unwrap_or(u16::MAX)
+            )
+        )?;
+        Ok(())
+    }
+
+    fn hide_cursor(&mut self) -> IOResult {
+        queue!(stdout(), Hide)?;
+        Ok(())
+    }
+
+    fn show_cursor(&mut self) -> IOResult {
+        queue!(stdout(), Show)?;
+        Ok(())
+    }
+
+    fn set_cursor_style(&mut self, style: SetCursorStyle) -> IOResult {
+        queue!(stdout(), style)?;
+        Ok(())
+    }
+
+    fn clear(&mut self, clear_type: ClearType) -> IOResult {
+        queue!(stdout(), Clear(clear_type))?;
+        Ok(())
+    }
+
+    fn set_style(&mut self, style: Style) -> IOResult {
+        queue!(stdout(), SetAttribute(Attribute::Reset), ResetColor)?;
+        if let Some(color) = style.foreground {
+            queue!(stdout(), SetForegroundColor(color))?;
+        }
+        if let Some(color) = style.background {
+            queue!(stdout(), SetBackgroundColor(color))?;
+        }
+        queue!(stdout(), SetAttributes(style.attributes))?;
+        Ok(())
+    }
+
+    fn write(&mut self, text: &str) -> IOResult {
+        stdout().write_all(text.as_bytes())?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> IOResult {
+        stdout().flush()?;
+        Ok(())
+    }
+
+    fn set_clipboard(&mut self, text: &str) -> IOResult {
+        write!(stdout(), "\x1b]52;c;{}\x07", base64(text.as_bytes()))?;
+        Ok(())
+    }
+
+    fn read_event(&mut self) -> Result<Option<Event>, std::io::Error> {
+        Ok(Some(read()?))
+    }
+
+    fn poll_event(&mut self, timeout: Duration) -> Result<bool, std::io::Error> {
+        poll(timeout)
+    }
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let group = chunk.iter().enumerate().fold(0, |group, (index, byte)| {
+            group | u32::from(*byte) << (16 - 8 * index)
+        });
+        for index in 0..4 {
+            if index <= chunk.len() {
+                encoded.push(char::from(
+                    ALPHABET[(group >> (18 - 6 * index) & 0x3f) as usize],
+                ));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
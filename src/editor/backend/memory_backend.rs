@@ -0,0 +1,162 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::rc::Rc;
+use std::time::Duration;
+
+use crossterm::cursor::SetCursorStyle;
+use crossterm::event::Event;
+use crossterm::terminal::ClearType;
+
+use super::super::frame::{Frame, Style};
+use super::super::IOResult;
+use super::Backend;
+
+struct MemoryState {
+    screen: Frame,
+    style: Style,
+    cursor_style: SetCursorStyle,
+    events: VecDeque<Event>,
+    clipboard: Option<String>,
+}
+
+/// A headless backend that draws into an in-memory grid of cells and reads
+/// events from a scripted queue. Clones share the same grid, so a clone kept
+/// outside the editor can inspect what was drawn.
+#[derive(Clone)]
+pub struct MemoryBackend {
+    state: Rc<RefCell<MemoryState>>,
+}
+
+impl MemoryBackend {
+    #[must_use]
+    pub fn new(width: usize, height: usize) -> Self {
+        MemoryBackend {
+            state: Rc::new(RefCell::new(MemoryState {
+                screen: Frame::new(width, height),
+                style: Style::default(),
+                cursor_style: SetCursorStyle::DefaultUserShape,
+                events: VecDeque::new(),
+                clipboard: None,
+            })),
+        }
+    }
+
+    pub fn push_event(&self, event: Event) {
+        self.state.borrow_mut().events.push_back(event);
+    }
+
+    #[must_use]
+    pub fn lines(&self) -> Vec<String> {
+        let state = self.state.borrow();
+        (0..state.screen.size().1)
+            .map(|y| state.screen.row_text(y).trim_end().to_string())
+            .collect()
+    }
+
+    #[must_use]
+    pub fn cursor(&self) -> (usize, usize) {
+        self.state.borrow().screen.cursor()
+    }
+
+    #[must_use]
+    pub fn style_at(&self, x: usize, y: usize) -> Style {
+        self.state.borrow().screen.style_at(x, y)
+    }
+
+    #[must_use]
+    pub fn clipboard(&self) -> Option<String> {
+        self.state.borrow().clipboard.clone()
+    }
+
+    #[must_use]
+    pub fn cursor_style(&self) -> SetCursorStyle {
+        self.state.borrow().cursor_style
+    }
+}
+
+impl Backend for MemoryBackend {
+    fn init(&mut self) -> IOResult {
+        self.clear(ClearType::All)
+    }
+
+    fn clean_up(&mut self) -> IOResult {
+        Ok(())
+    }
+
+    fn size(&self) -> Result<(usize, usize), std::io::Error> {
+        Ok(self.state.borrow().screen.size())
+    }
+
+    fn move_cursor_to(&mut self, x: usize, y: usize) -> IOResult {
+        self.state.borrow_mut().screen.set_cursor(x, y);
+        Ok(())
+    }
+
+    fn hide_cursor(&mut self) -> IOResult {
+        Ok(())
+    }
+
+    fn show_cursor(&mut self) -> IOResult {
+        Ok(())
+    }
+
+    fn set_cursor_style(&mut self, style: SetCursorStyle) -> IOResult {
+        self.state.borrow_mut().cursor_style = style;
+        Ok(())
+    }
+
+    fn clear(&mut self, clear_type: ClearType) -> IOResult {
+        let screen = &mut self.state.borrow_mut().screen;
+        let (x, y) = screen.cursor();
+        let (_width, height) = screen.size();
+        let rows = match clear_type {
+            ClearType::All | ClearType::Purge => 0..height,
+            ClearType::FromCursorDown => {
+                screen.clear_row(y, x);
+                y + 1..height
+            }
+            ClearType::FromCursorUp => 0..y + 1,
+            ClearType::CurrentLine => y..y + 1,
+            ClearType::UntilNewLine => {
+                screen.clear_row(y, x);
+                0..0
+            }
+        };
+        for row in rows {
+            screen.clear_row(row, 0);
+        }
+        Ok(())
+    }
+
+    fn set_style(&mut self, style: Style) -> IOResult {
+        self.state.borrow_mut().style = style;
+        Ok(())
+    }
+
+    fn write(&mut self, text: &str) -> IOResult {
+        let state = &mut *self.state.borrow_mut();
+        let (x, y) = state.screen.cursor();
+        let x = state.screen.print(x, y, text, state.style);
+        state.screen.set_cursor(x, y);
+        Ok(())
+    }
+
+    fn flush(&mut self) -> IOResult {
+        Ok(())
+    }
+
+    fn set_clipboard(&mut self, text: &str) -> IOResult {
+        self.state.borrow_mut().clipboard = Some(String::from(text));
+        Ok(())
+    }
+
+    fn read_event(&mut self) -> Result<Option<Event>, std::io::Error> {
+        Ok(self.state.borrow_mut().events.pop_front())
+    }
+
+    /// Never waits, as scripted events are either queued already or never
+    /// come.
+    fn poll_event(&mut self, _timeout: Duration) -> Result<bool, std::io::Error> {
+        Ok(!self.state.borrow().events.is_empty())
+    }
+}
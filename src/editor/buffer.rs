@@ -1,61 +1,354 @@
-use std::fs;
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{BufWriter, ErrorKind};
+use std::ops::Range;
 
+use regex::Regex;
+use ropey::Rope;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::highlight::{Highlighter, Span};
+use super::history::{Edit, History};
 use super::terminal::Terminal;
-use super::{screen, IOResult};
+use super::IOResult;
+
+/// A line inserted or deleted at a row, kept until taken so that positions
+/// held outside the buffer can be moved with its lines.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LineEdit {
+    Insert(usize),
+    Delete(usize),
+}
 
-pub struct Buffer<'a> {
-    contents: Vec<String>,
-    file_path: Option<&'a String>,
+pub struct Buffer {
+    contents: Rope,
+    file_path: Option<String>,
+    modified: bool,
+    changes: usize,
+    history: History,
+    highlighter: Highlighter,
+    marks: HashMap<char, (usize, usize)>,
+    line_edits: Vec<LineEdit>,
 }
 
-impl<'a> Default for Buffer<'a> {
+impl Default for Buffer {
     fn default() -> Self {
         Buffer {
-            contents: vec![String::new()],
+            contents: Rope::new(),
             file_path: None,
+            modified: false,
+            changes: 0,
+            history: History::default(),
+            highlighter: Highlighter::default(),
+            marks: HashMap::new(),
+            line_edits: Vec::new(),
         }
     }
 }
 
-impl<'a> Buffer<'a> {
+#[allow(
+    clippy::len_without_is_empty,
+    clippy::missing_errors_doc,
+    clippy::missing_panics_doc,
+    clippy::must_use_candidate
+)]
+impl Buffer {
     pub fn new() -> Self {
         Buffer::default()
     }
 
-    pub fn read_file(&mut self, file: Option<&'a String>) -> IOResult {
-        self.file_path = file;
+    pub fn read_file(&mut self, file: Option<&str>) -> IOResult {
+        self.file_path = file.map(String::from);
+        self.contents = Rope::new();
+        self.modified = false;
+        self.history = History::default();
+        self.line_edits.clear();
+        self.highlighter = Highlighter::for_path(file);
         if let Some(path) = file {
-            let contents = fs::read_to_string(path).expect("Can't open file");
-            self.contents = contents.lines().map(|val| String::from(val)).collect();
+            match fs::read_to_string(path) {
+                Ok(contents) => {
+                    let contents = contents.replace("\r\n", "\n");
+                    let contents = contents.strip_suffix('\n').unwrap_or(&contents);
+                    self.contents = Rope::from_str(contents);
+                }
+                Err(err) if err.kind() == ErrorKind::NotFound => (),
+                Err(err) => return Err(err),
+            }
         }
         Ok(())
     }
 
-    fn write_to_file(&self, file_path: &String) -> IOResult {
-        fs::write(file_path, self.contents.join("\n"))?;
+    fn write_to_file(&self, file_path: &str) -> IOResult {
+        self.contents
+            .write_to(BufWriter::new(File::create(file_path)?))?;
         Ok(())
     }
 
-    pub fn write_file(&self, file_path: Option<&String>) -> IOResult {
+    pub fn write_file(&mut self, file_path: Option<&str>) -> IOResult {
         if let Some(path) = file_path {
             self.write_to_file(path)?;
-        } else if let Some(path) = self.file_path {
+            if self.file_path.is_none() {
+                self.set_file_path(Some(path));
+            }
+        } else if let Some(path) = &self.file_path {
             self.write_to_file(path)?;
         } else {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
-                "No file to write to",
+                "No file name",
             ));
         }
+        if file_path.is_none() || file_path == self.file_path.as_deref() {
+            self.modified = false;
+        }
         Ok(())
     }
 
-    pub fn get_line(&self, i: usize) -> Option<&String> {
-        self.contents.get(i)
+    pub fn file_path(&self) -> Option<&str> {
+        self.file_path.as_deref()
+    }
+
+    pub fn name(&self) -> &str {
+        self.file_path.as_deref().unwrap_or("[No Name]")
+    }
+
+    pub fn set_file_path(&mut self, file_path: Option<&str>) {
+        self.file_path = file_path.map(String::from);
+        self.highlighter = Highlighter::for_path(file_path);
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    /// How many edits have been made, not counting undo and redo.
+    pub fn changes(&self) -> usize {
+        self.changes
+    }
+
+    pub fn mark(&self, name: char) -> Option<(usize, usize)> {
+        self.marks.get(&name).copied()
+    }
+
+    pub fn set_mark(&mut self, name: char, position: (usize, usize)) {
+        self.marks.insert(name, position);
+    }
+
+    pub fn remove_mark(&mut self, name: char) {
+        self.marks.remove(&name);
+    }
+
+    /// The lines inserted and deleted since this was last called, in order.
+    pub fn take_line_edits(&mut self) -> Vec<LineEdit> {
+        std::mem::take(&mut self.line_edits)
+    }
+
+    /// Keeps marks on their lines when a line is inserted or deleted before
+    /// them, and drops the marks of a deleted line. Marks after text inserted
+    /// or deleted on their line keep to the same text, and marks in deleted
+    /// text move to where it was.
+    fn move_marks(&mut self, edit: &Edit) {
+        match *edit {
+            Edit::InsertLine { row, .. } => {
+                self.line_edits.push(LineEdit::Insert(row));
+                for (_column, mark_row) in self.marks.values_mut() {
+                    if *mark_row >= row {
+                        *mark_row += 1;
+                    }
+                }
+            }
+            Edit::DeleteLine { row, .. } => {
+                self.line_edits.push(LineEdit::Delete(row));
+                self.marks
+                    .retain(|_name, (_column, mark_row)| *mark_row != row);
+                for (_column, mark_row) in self.marks.values_mut() {
+                    if *mark_row > row {
+                        *mark_row -= 1;
+                    }
+                }
+            }
+            Edit::InsertText {
+                row,
+                column,
+                ref text,
+            } => {
+                let (start, _row) = self.grapheme_position((column, row));
+                let (end, _row) = self.grapheme_position((column + text.len(), row));
+                for (mark_column, mark_row) in self.marks.values_mut() {
+                    if *mark_row == row && *mark_column > start {
+                        *mark_column += end - start;
+                    }
+                }
+            }
+            Edit::DeleteText {
+                row,
+                column,
+                ref text,
+            } => {
+                let (start, _row) = self.grapheme_position((column, row));
+                let removed = text.graphemes(true).count();
+                for (mark_column, mark_row) in self.marks.values_mut() {
+                    if *mark_row == row && *mark_column > start {
+                        *mark_column = mark_column.saturating_sub(removed).max(start);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn get_line(&self, i: usize) -> Option<Cow<'_, str>> {
+        let line = self.contents.get_line(i)?;
+        let len = line.len_chars();
+        if len > 0 && line.char(len - 1) == '\n' {
+            return Some(line.slice(..len - 1).into());
+        }
+        Some(line.into())
+    }
+
+    pub fn highlight_line(&self, i: usize) -> Vec<Span> {
+        self.highlighter
+            .highlight_line(i, |row| self.get_line(row).unwrap_or_default())
+    }
+
+    pub fn line_len(&self, i: usize) -> usize {
+        self.get_line(i)
+            .map_or(0, |line| line.graphemes(true).count())
+    }
+
+    pub fn find(
+        &self,
+        regex: &Regex,
+        column: usize,
+        row: usize,
+        forward: bool,
+    ) -> Option<(usize, usize)> {
+        if row >= self.len() {
+            return None;
+        }
+        let len = self.len();
+        let line = self.get_line(row)?;
+        let last_before = |line: &str, end: usize| {
+            regex
+                .find_iter(line)
+                .take_while(|found| found.start() < end)
+                .last()
+                .map(|found| found.start())
+        };
+        let found = if forward {
+            let start = Self::byte_index(&line, column + 1);
+            regex
+                .find_at(&line, start)
+                .map(|found| (found.start(), row))
+                .or_else(|| {
+                    (1..=len).find_map(|offset| {
+                        let row = (row + offset) % len;
+                        let index = regex.find(&self.get_line(row)?)?.start();
+                        Some((index, row))
+                    })
+                })
+        } else {
+            let end = Self::byte_index(&line, column);
+            last_before(&line, end)
+                .map(|index| (index, row))
+                .or_else(|| {
+                    (1..=len).find_map(|offset| {
+                        let row = (row + len - offset) % len;
+                        let line = self.get_line(row)?;
+                        Some((last_before(&line, line.len() + 1)?, row))
+                    })
+                })
+        };
+        found.map(|position| self.grapheme_position(position))
+    }
+
+    fn byte_index(line: &str, column: usize) -> usize {
+        line.grapheme_indices(true)
+            .nth(column)
+            .map_or(line.len(), |(index, _)| index)
+    }
+
+    pub fn grapheme_position(&self, (column, row): (usize, usize)) -> (usize, usize) {
+        let line = self.get_line(row).unwrap_or_default();
+        let column = line
+            .grapheme_indices(true)
+            .take_while(|(index, grapheme)| index + grapheme.len() <= column)
+            .count();
+        (column, row)
+    }
+
+    fn char_index(&self, row: usize, column: usize) -> usize {
+        self.contents
+            .byte_to_char(self.contents.line_to_byte(row) + column)
+    }
+
+    fn apply_edit(&mut self, edit: &Edit) {
+        match edit {
+            Edit::InsertText { row, column, text } => {
+                let index = self.char_index(*row, *column);
+                self.contents.insert(index, text);
+            }
+            Edit::DeleteText { row, column, text } => {
+                let index = self.char_index(*row, *column);
+                self.contents.remove(index..index + text.chars().count());
+            }
+            Edit::InsertLine { row, text } => {
+                if *row < self.len() {
+                    let index = self.contents.line_to_char(*row);
+                    self.contents.insert(index, &format!("{text}\n"));
+                } else {
+                    let index = self.contents.len_chars();
+                    self.contents.insert(index, &format!("\n{text}"));
+                }
+            }
+            Edit::DeleteLine { row, .. } => {
+                let start = self.contents.line_to_char(*row);
+                if *row + 1 < self.len() {
+                    let end = self.contents.line_to_char(*row + 1);
+                    self.contents.remove(start..end);
+                } else {
+                    let end = self.contents.len_chars();
+                    self.contents.remove(start - 1..end);
+                }
+            }
+        }
+        self.highlighter.invalidate(edit.position().1);
+        self.move_marks(edit);
+        self.modified = true;
+    }
+
+    fn edit(&mut self, edit: Edit) {
+        self.apply_edit(&edit);
+        self.history.record(edit);
+        self.changes += 1;
+    }
+
+    pub fn begin_undo_group(&mut self) {
+        self.history.begin_group();
+    }
+
+    pub fn end_undo_group(&mut self) {
+        self.history.end_group();
+    }
+
+    pub fn undo(&mut self) -> Option<(usize, usize)> {
+        let edits = self.history.undo()?.clone();
+        for edit in edits.iter().rev() {
+            self.apply_edit(&edit.inverse());
+        }
+        edits
+            .first()
+            .map(|edit| self.grapheme_position(edit.position()))
     }
 
-    pub fn get_line_mut(&mut self, i: usize) -> Option<&mut String> {
-        self.contents.get_mut(i)
+    pub fn redo(&mut self) -> Option<(usize, usize)> {
+        let edits = self.history.redo()?.clone();
+        for edit in &edits {
+            self.apply_edit(edit);
+        }
+        edits
+            .first()
+            .map(|edit| self.grapheme_position(edit.position()))
     }
 
     pub fn insert_char_on_line(
@@ -65,62 +358,104 @@ impl<'a> Buffer<'a> {
         row: usize,
         column: usize,
     ) -> IOResult {
-        if row > self.len() {
+        if row >= self.len() {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
                 "Row doesn't exist",
             ));
         }
-        let line = self.get_line_mut(row).unwrap();
-        if column > line.len() {
+        if column > self.line_len(row) {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
                 "Column out of Bounds",
             ));
         }
-        (*line).insert(column as usize, char);
-        terminal.move_right_for_insert(&self)?;
-        screen::update_line(self, terminal, row)?;
+        let column = Self::byte_index(&self.get_line(row).unwrap(), column);
+        self.edit(Edit::InsertText {
+            row,
+            column,
+            text: char.to_string(),
+        });
+        let (column, row) = self.grapheme_position((column + char.len_utf8(), row));
+        terminal.goto(self, column, row)?;
         Ok(())
     }
 
-    pub fn delete_char_on_line(
-        &mut self,
-        terminal: &Terminal,
-        row: usize,
-        column: usize,
-    ) -> IOResult {
-        if row > self.len() {
+    pub fn delete_char_on_line(&mut self, row: usize, column: usize) -> IOResult {
+        if row >= self.len() {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
                 "Row doesn't exist",
             ));
         }
-        let line = self.get_line_mut(row).unwrap();
-        if column > line.len() {
+        let line = self.get_line(row).unwrap();
+        let Some((column, text)) = line.grapheme_indices(true).nth(column) else {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
                 "Column out of Bounds",
             ));
+        };
+        let text = String::from(text);
+        self.edit(Edit::DeleteText { row, column, text });
+        Ok(())
+    }
+
+    pub fn replace_text(&mut self, row: usize, byte_range: Range<usize>, text: &str) -> IOResult {
+        let Some(line) = self.get_line(row) else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Row doesn't exist",
+            ));
+        };
+        let Some(removed) = line.get(byte_range.clone()).map(String::from) else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Column out of Bounds",
+            ));
+        };
+        self.history.begin_group();
+        if !removed.is_empty() {
+            self.edit(Edit::DeleteText {
+                row,
+                column: byte_range.start,
+                text: removed,
+            });
+        }
+        if !text.is_empty() {
+            self.edit(Edit::InsertText {
+                row,
+                column: byte_range.start,
+                text: String::from(text),
+            });
         }
-        (*line).remove(column as usize);
-        screen::update_current_line(&self, terminal)?;
+        self.history.end_group();
         Ok(())
     }
 
     pub fn delete_line(&mut self, row: usize) -> IOResult {
-        if row > self.len() {
+        if row >= self.len() {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
                 "Row doesn't exist",
             ));
         }
-        self.contents.splice(row..row + 1, []).for_each(drop);
+        let text = self.get_line(row).unwrap().into_owned();
+        if self.len() == 1 {
+            self.edit(Edit::DeleteText {
+                row,
+                column: 0,
+                text,
+            });
+        } else {
+            self.edit(Edit::DeleteLine { row, text });
+        }
         Ok(())
     }
 
+    /// Appends line `row` to the line above, moving its marks along, ready
+    /// for `row` to be deleted.
     pub fn move_line_contents_up_one_row(&mut self, row: usize) -> IOResult {
-        if row > self.len() {
+        if row >= self.len() {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::InvalidInput,
                 "Row doesn't exist",
@@ -132,10 +467,47 @@ impl<'a> Buffer<'a> {
                 "Top Row can't be moved up",
             ));
         }
+        let text = self.get_line(row).unwrap().into_owned();
+        let column = self.get_line(row - 1).unwrap().len();
+        let offset = self.line_len(row - 1);
+        self.edit(Edit::InsertText {
+            row: row - 1,
+            column,
+            text,
+        });
+        for (mark_column, mark_row) in self.marks.values_mut() {
+            if *mark_row == row {
+                *mark_row = row - 1;
+                *mark_column += offset;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn split_line(&mut self, row: usize, column: usize) -> IOResult {
+        if row >= self.len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Row doesn't exist",
+            ));
+        }
+        if column > self.line_len(row) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Column out of Bounds",
+            ));
+        }
         let line = self.get_line(row).unwrap();
-        let append_str = line.clone();
-        let line_upper = self.get_line_mut(row - 1).unwrap();
-        (*line_upper).push_str(append_str.as_str());
+        let column = Self::byte_index(&line, column);
+        let text = String::from(&line[column..]);
+        self.history.begin_group();
+        self.edit(Edit::DeleteText {
+            row,
+            column,
+            text: text.clone(),
+        });
+        self.insert_line(row + 1, text)?;
+        self.history.end_group();
         Ok(())
     }
 
@@ -146,11 +518,136 @@ impl<'a> Buffer<'a> {
                 "Out of Bounds",
             ));
         }
-        self.contents.insert(row as usize, contents);
+        self.edit(Edit::InsertLine {
+            row,
+            text: contents,
+        });
         Ok(())
     }
 
+    fn position_to_char(&self, (column, row): (usize, usize)) -> usize {
+        let line = self.get_line(row).unwrap_or_default();
+        self.char_index(row, Self::byte_index(&line, column))
+    }
+
+    /// The text between two grapheme positions, not including `end`.
+    pub fn text_range(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        let start = self.position_to_char(start);
+        let end = self.position_to_char(end);
+        self.contents.slice(start..end).to_string()
+    }
+
+    /// Deletes the text between two grapheme positions as one undo step,
+    /// joining the lines at either end, and returns the deleted text.
+    pub fn delete_range(
+        &mut self,
+        (start_column, start_row): (usize, usize),
+        (end_column, end_row): (usize, usize),
+    ) -> Result<String, std::io::Error> {
+        if end_row >= self.len() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Row doesn't exist",
+            ));
+        }
+        let removed = self.text_range((start_column, start_row), (end_column, end_row));
+        let start_line = self.get_line(start_row).unwrap_or_default().into_owned();
+        let start = Self::byte_index(&start_line, start_column);
+        if start_row == end_row {
+            let end = Self::byte_index(&start_line, end_column);
+            self.replace_text(start_row, start..end, "")?;
+            return Ok(removed);
+        }
+        self.history.begin_group();
+        for _ in start_row + 1..end_row {
+            self.delete_line(start_row + 1)?;
+        }
+        let end = Self::byte_index(
+            &self.get_line(start_row + 1).unwrap_or_default(),
+            end_column,
+        );
+        self.replace_text(start_row + 1, 0..end, "")?;
+        self.replace_text(start_row, start..start_line.len(), "")?;
+        self.move_line_contents_up_one_row(start_row + 1)?;
+        self.delete_line(start_row + 1)?;
+        self.history.end_group();
+        Ok(removed)
+    }
+
+    /// Inserts `text`, which may span several lines, at a grapheme position
+    /// as one undo step and returns the position just after it.
+    pub fn insert_text(
+        &mut self,
+        (column, row): (usize, usize),
+        text: &str,
+    ) -> Result<(usize, usize), std::io::Error> {
+        let Some(line) = self.get_line(row).map(Cow::into_owned) else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Row doesn't exist",
+            ));
+        };
+        let start = Self::byte_index(&line, column);
+        let mut pieces: Vec<&str> = text.split('\n').collect();
+        let last = pieces.pop().unwrap_or_default();
+        if pieces.is_empty() {
+            self.replace_text(row, start..start, last)?;
+            return Ok(self.grapheme_position((start + last.len(), row)));
+        }
+        self.history.begin_group();
+        self.replace_text(row, start..line.len(), pieces[0])?;
+        for (offset, piece) in pieces.iter().enumerate().skip(1) {
+            self.insert_line(row + offset, String::from(*piece))?;
+        }
+        let end_row = row + pieces.len();
+        self.insert_line(end_row, format!("{last}{}", &line[start..]))?;
+        self.history.end_group();
+        Ok(self.grapheme_position((last.len(), end_row)))
+    }
+
+    /// Deletes the lines `start..=end` as one undo step and returns their
+    /// text, one line per `\n`-terminated line.
+    pub fn delete_lines(&mut self, start: usize, end: usize) -> Result<String, std::io::Error> {
+        let removed = self.lines_text(start, end);
+        self.history.begin_group();
+        for _ in start..=end {
+            self.delete_line(start)?;
+        }
+        self.history.end_group();
+        Ok(removed)
+    }
+
+    pub fn lines_text(&self, start: usize, end: usize) -> String {
+        let mut text = String::new();
+        for line in (start..=end).filter_map(|row| self.get_line(row)) {
+            text.push_str(&line);
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn indent_line(&mut self, row: usize, indent: &str) -> IOResult {
+        if self.line_len(row) == 0 {
+            return Ok(());
+        }
+        self.replace_text(row, 0..0, indent)
+    }
+
+    /// Removes up to `width` leading spaces, or one leading tab.
+    pub fn dedent_line(&mut self, row: usize, width: usize) -> IOResult {
+        let line = self.get_line(row).unwrap_or_default();
+        let end = if line.starts_with('\t') {
+            1
+        } else {
+            line.bytes()
+                .take(width)
+                .take_while(|byte| *byte == b' ')
+                .count()
+        };
+        self.replace_text(row, 0..end, "")
+    }
+
     pub fn len(&self) -> usize {
-        self.contents.len()
+        self.contents.len_lines()
     }
 }
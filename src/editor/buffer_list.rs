@@ -0,0 +1,134 @@
+use super::buffer::Buffer;
+
+struct Entry {
+    number: usize,
+    buffer: Buffer,
+    cursor: (usize, usize),
+    position: (usize, usize),
+}
+
+/// The open buffers, each remembering where its cursor and scroll were when
+/// it was last shown.
+pub struct BufferList {
+    entries: Vec<Entry>,
+    current: usize,
+    next_number: usize,
+}
+
+impl Default for BufferList {
+    fn default() -> Self {
+        let mut buffers = BufferList {
+            entries: Vec::new(),
+            current: 0,
+            next_number: 1,
+        };
+        buffers.push(Buffer::new());
+        buffers
+    }
+}
+
+impl BufferList {
+    pub fn new() -> Self {
+        BufferList::default()
+    }
+
+    fn push(&mut self, buffer: Buffer) -> usize {
+        self.entries.push(Entry {
+            number: self.next_number,
+            buffer,
+            cursor: (0, 0),
+            position: (0, 0),
+        });
+        self.next_number += 1;
+        self.entries.len() - 1
+    }
+
+    pub fn open(&mut self, file_path: &str) -> Result<usize, std::io::Error> {
+        let mut buffer = Buffer::new();
+        buffer.read_file(Some(file_path))?;
+        Ok(self.push(buffer))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn current(&self) -> &Buffer {
+        &self.entries[self.current].buffer
+    }
+
+    pub fn current_mut(&mut self) -> &mut Buffer {
+        &mut self.entries[self.current].buffer
+    }
+
+    pub fn current_index(&self) -> usize {
+        self.current
+    }
+
+    pub fn select(&mut self, index: usize) {
+        self.current = index;
+    }
+
+    pub fn number(&self, index: usize) -> usize {
+        self.entries[index].number
+    }
+
+    pub fn get(&self, index: usize) -> &Buffer {
+        &self.entries[index].buffer
+    }
+
+    pub fn index_of_number(&self, number: usize) -> Option<usize> {
+        self.entries.iter().position(|entry| entry.number == number)
+    }
+
+    pub fn index_of_path(&self, file_path: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|entry| entry.buffer.file_path() == Some(file_path))
+    }
+
+    /// The buffer holding the file-global mark `name`.
+    pub fn index_of_mark(&self, name: char) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|entry| entry.buffer.mark(name).is_some())
+    }
+
+    /// Sets a file-global mark in the current buffer, moving it from any
+    /// other buffer that had it.
+    pub fn set_global_mark(&mut self, name: char, position: (usize, usize)) {
+        for entry in &mut self.entries {
+            entry.buffer.remove_mark(name);
+        }
+        self.current_mut().set_mark(name, position);
+    }
+
+    pub fn modified_index(&self) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|entry| entry.buffer.is_modified())
+    }
+
+    pub fn save_view(&mut self, cursor: (usize, usize), position: (usize, usize)) {
+        let entry = &mut self.entries[self.current];
+        entry.cursor = cursor;
+        entry.position = position;
+    }
+
+    pub fn view(&self, index: usize) -> ((usize, usize), (usize, usize)) {
+        let entry = &self.entries[index];
+        (entry.cursor, entry.position)
+    }
+
+    /// Removes the buffer at `index`, leaving an empty buffer behind if it
+    /// was the last one.
+    pub fn remove(&mut self, index: usize) {
+        self.entries.remove(index);
+        if self.entries.is_empty() {
+            self.push(Buffer::new());
+        }
+        if self.current > index || self.current >= self.entries.len() {
+            self.current = self.current.saturating_sub(1);
+        }
+    }
+}
@@ -0,0 +1,326 @@
+use regex::Captures;
+
+use super::keymap::MapMode;
+
+pub enum Command {
+    Substitute(Substitution),
+    Write(Option<String>),
+    Quit {
+        force: bool,
+    },
+    QuitAll {
+        force: bool,
+    },
+    WriteQuit(Option<String>),
+    Exit,
+    Edit {
+        file_path: Option<String>,
+        force: bool,
+    },
+    SaveAs(String),
+    NextBuffer,
+    PreviousBuffer,
+    Buffer(usize),
+    ListBuffers,
+    DeleteBuffer {
+        number: Option<usize>,
+        force: bool,
+    },
+    Split(Option<String>),
+    VerticalSplit(Option<String>),
+    Close,
+    Set(Vec<String>),
+    Map {
+        modes: Vec<MapMode>,
+        keys: Option<String>,
+        action: Option<String>,
+    },
+    Unmap {
+        modes: Vec<MapMode>,
+        keys: String,
+    },
+}
+
+/// A line address; `Line` is 1-based and the others carry a line offset.
+#[derive(Clone, Copy)]
+pub enum Address {
+    Line(isize),
+    Current(isize),
+    Last(isize),
+}
+
+#[derive(Clone, Copy)]
+pub enum Range {
+    Whole,
+    Lines(Address, Address),
+}
+
+impl Range {
+    /// Resolves the range to inclusive 0-based rows.
+    pub fn resolve(self, current: usize, len: usize) -> Result<(usize, usize), String> {
+        let (start, end) = match self {
+            Range::Whole => return Ok((0, len - 1)),
+            Range::Lines(start, end) => (start.resolve(current, len), end.resolve(current, len)),
+        };
+        let (start, end) = (isize::min(start, end), isize::max(start, end));
+        let row = |line: isize| {
+            line.checked_sub(1)
+                .and_then(|row| usize::try_from(row).ok())
+        };
+        match (row(start), row(end)) {
+            (Some(start), Some(end)) if end < len => Ok((start, end)),
+            _ => Err(String::from("Invalid range")),
+        }
+    }
+}
+
+impl Address {
+    fn resolve(self, current: usize, len: usize) -> isize {
+        let line = |row: usize| isize::try_from(row).unwrap_or(isize::MAX);
+        match self {
+            Address::Line(line) => line,
+            Address::Current(offset) => line(current + 1).saturating_add(offset),
+            Address::Last(offset) => line(len).saturating_add(offset),
+        }
+    }
+}
+
+pub struct Substitution {
+    pub range: Option<Range>,
+    pub pattern: String,
+    pub replacement: String,
+    pub global: bool,
+    pub ignore_case: bool,
+    pub confirm: bool,
+}
+
+impl Substitution {
+    /// Builds the replacement for one match, where `&` and `\0` stand for the
+    /// whole match and `\1` to `\9` for its capture groups.
+    pub fn expand(&self, captures: &Captures) -> String {
+        let group = |index| captures.get(index).map_or("", |group| group.as_str());
+        let mut text = String::new();
+        let mut chars = self.replacement.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '&' => text.push_str(group(0)),
+                '\\' => match chars.next() {
+                    Some(next) => match next.to_digit(10) {
+                        Some(index) => text.push_str(group(index as usize)),
+                        None => text.push(next),
+                    },
+                    None => text.push(c),
+                },
+                _ => text.push(c),
+            }
+        }
+        text
+    }
+}
+
+pub fn parse(text: &str) -> Result<Command, String> {
+    let text = text.trim();
+    let (range, rest) = parse_range(text);
+    let name_len = rest
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(rest.len());
+    if matches!(&rest[..name_len], "s" | "substitute") {
+        return parse_substitute(range, &rest[name_len..]);
+    }
+    if range.is_some() {
+        return Err(String::from("No range allowed"));
+    }
+    let (name, argument) = match text.split_once(char::is_whitespace) {
+        Some((name, argument)) => (name, Some(argument.trim())),
+        None => (text, None),
+    };
+    let (name, force) = match name.strip_suffix('!') {
+        Some(name) => (name, true),
+        None => (name, false),
+    };
+    let argument = argument.filter(|arg| !arg.is_empty());
+    let file_path = argument.map(String::from);
+    let number = || {
+        argument
+            .map(|arg| {
+                arg.parse::<usize>()
+                    .map_err(|_| format!("Invalid argument: {arg}"))
+            })
+            .transpose()
+    };
+
+    match name {
+        "w" | "write" => Ok(Command::Write(file_path)),
+        "q" | "quit" => Ok(Command::Quit { force }),
+        "qa" | "qall" => Ok(Command::QuitAll { force }),
+        "wq" => Ok(Command::WriteQuit(file_path)),
+        "x" | "xit" => Ok(Command::Exit),
+        "e" | "edit" => Ok(Command::Edit { file_path, force }),
+        "sav" | "saveas" => match file_path {
+            Some(path) => Ok(Command::SaveAs(path)),
+            None => Err(String::from("Argument required")),
+        },
+        "bn" | "bnext" => Ok(Command::NextBuffer),
+        "bp" | "bprevious" | "bN" | "bNext" => Ok(Command::PreviousBuffer),
+        "b" | "buffer" => match number()? {
+            Some(number) => Ok(Command::Buffer(number)),
+            None => Err(String::from("Argument required")),
+        },
+        "ls" | "buffers" | "files" => Ok(Command::ListBuffers),
+        "bd" | "bdelete" => Ok(Command::DeleteBuffer {
+            number: number()?,
+            force,
+        }),
+        "sp" | "split" => Ok(Command::Split(file_path)),
+        "vs" | "vsplit" => Ok(Command::VerticalSplit(file_path)),
+        "clo" | "close" => Ok(Command::Close),
+        "se" | "set" => match argument {
+            Some(arg) => Ok(Command::Set(
+                arg.split_whitespace().map(String::from).collect(),
+            )),
+            None => Err(String::from("Argument required")),
+        },
+        "" => Err(String::new()),
+        _ => parse_map(name, argument).ok_or_else(|| format!("Not an editor command: {text}"))?,
+    }
+}
+
+/// Parses `:map` and `:unmap`, which may start with the letter of a mode, as
+/// in `:nmap` and `:iunmap`. `:noremap` and its variants are the same as
+/// `:map`, as the keys a mapping types are never mapped again.
+fn parse_map(name: &str, argument: Option<&str>) -> Option<Result<Command, String>> {
+    let prefix = name.strip_suffix("map")?;
+    if let Some(prefix) = prefix.strip_suffix("un") {
+        let modes = MapMode::from_prefix(prefix)?;
+        return Some(match argument {
+            Some(keys) => Ok(Command::Unmap {
+                modes,
+                keys: String::from(keys),
+            }),
+            None => Err(String::from("Argument required")),
+        });
+    }
+    let modes = MapMode::from_prefix(prefix.strip_suffix("nore").unwrap_or(prefix))?;
+    let (keys, action) = match argument.map(|arg| arg.split_once(char::is_whitespace)) {
+        Some(Some((keys, action))) => (Some(keys), Some(action.trim())),
+        Some(None) => (argument, None),
+        None => (None, None),
+    };
+    Some(Ok(Command::Map {
+        modes,
+        keys: keys.map(String::from),
+        action: action.map(String::from),
+    }))
+}
+
+fn parse_range(text: &str) -> (Option<Range>, &str) {
+    if let Some(rest) = text.strip_prefix('%') {
+        return (Some(Range::Whole), rest);
+    }
+    let (Some(start), rest) = parse_address(text) else {
+        return (None, text);
+    };
+    match rest.strip_prefix(',') {
+        Some(rest) => {
+            let (end, rest) = parse_address(rest);
+            (
+                Some(Range::Lines(start, end.unwrap_or(Address::Current(0)))),
+                rest,
+            )
+        }
+        None => (Some(Range::Lines(start, start)), rest),
+    }
+}
+
+fn parse_address(text: &str) -> (Option<Address>, &str) {
+    let digits = |text: &str| {
+        text.find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(text.len())
+    };
+    let (mut address, mut rest) = if let Some(rest) = text.strip_prefix('.') {
+        (Some(Address::Current(0)), rest)
+    } else if let Some(rest) = text.strip_prefix('$') {
+        (Some(Address::Last(0)), rest)
+    } else if digits(text) > 0 {
+        let (line, rest) = text.split_at(digits(text));
+        (
+            Some(Address::Line(line.parse().unwrap_or(isize::MAX))),
+            rest,
+        )
+    } else {
+        (None, text)
+    };
+    while let Some(sign) = rest.chars().next().filter(|c| *c == '+' || *c == '-') {
+        let (count, after) = rest[1..].split_at(digits(&rest[1..]));
+        let count: isize = if count.is_empty() {
+            1
+        } else {
+            count.parse().unwrap_or(isize::MAX)
+        };
+        let offset = if sign == '+' { count } else { -count };
+        address = Some(match address.unwrap_or(Address::Current(0)) {
+            Address::Line(line) => Address::Line(line.saturating_add(offset)),
+            Address::Current(current) => Address::Current(current.saturating_add(offset)),
+            Address::Last(last) => Address::Last(last.saturating_add(offset)),
+        });
+        rest = after;
+    }
+    (address, rest)
+}
+
+fn parse_substitute(range: Option<Range>, argument: &str) -> Result<Command, String> {
+    let Some(delimiter) = argument.chars().next() else {
+        return Err(String::from("Argument required"));
+    };
+    if delimiter.is_alphanumeric() || delimiter.is_whitespace() || "\\\"|".contains(delimiter) {
+        return Err(String::from(
+            "Regular expression can't be delimited by letters",
+        ));
+    }
+    let (pattern, rest) = split_delimited(&argument[delimiter.len_utf8()..], delimiter);
+    let (replacement, rest) = split_delimited(rest.unwrap_or_default(), delimiter);
+    let flags = rest.unwrap_or_default().trim();
+    let mut substitution = Substitution {
+        range,
+        pattern,
+        replacement,
+        global: false,
+        ignore_case: false,
+        confirm: false,
+    };
+    for flag in flags.chars() {
+        match flag {
+            'g' => substitution.global = true,
+            'c' => substitution.confirm = true,
+            'i' => substitution.ignore_case = true,
+            'I' => substitution.ignore_case = false,
+            _ => return Err(format!("Trailing characters: {flags}")),
+        }
+    }
+    Ok(Command::Substitute(substitution))
+}
+
+/// Splits `text` at the first unescaped `delimiter`, unescaping escaped
+/// delimiters in the part before it.
+fn split_delimited(text: &str, delimiter: char) -> (String, Option<&str>) {
+    let mut part = String::new();
+    let mut chars = text.char_indices();
+    while let Some((index, c)) = chars.next() {
+        if c == delimiter {
+            return (part, Some(&text[index + c.len_utf8()..]));
+        }
+        if c == '\\' {
+            match chars.next() {
+                Some((_, next)) if next == delimiter => part.push(next),
+                Some((_, next)) => {
+                    part.push(c);
+                    part.push(next);
+                }
+                None => part.push(c),
+            }
+        } else {
+            part.push(c);
+        }
+    }
+    (part, None)
+}
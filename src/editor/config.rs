@@ -0,0 +1,173 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use super::options::Value;
+
+/// One `key = value` line of the config file, with the table it is in. Keys
+/// outside any table have an empty `table`.
+pub struct Entry {
+    pub line: usize,
+    pub table: String,
+    pub key: String,
+    pub value: Value,
+}
+
+/// Where the config file is: `text-editor/config.toml` in
+/// `$XDG_CONFIG_HOME`, or in `~/.config` if that isn't set.
+pub fn path() -> Option<PathBuf> {
+    let directory = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|directory| !directory.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(directory.join("text-editor").join("config.toml"))
+}
+
+/// Reads the config file at `path`. A missing file has no entries.
+pub fn read(path: &Path) -> Result<Vec<Entry>, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => parse(&text),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// Parses the subset of TOML the config file is written in, one item per
+/// line:
+///
+/// - `[table]` headers, whose name may be dotted, as in `[keymap.insert]`.
+/// - `key = value` pairs, where the key is bare (`A-Za-z0-9_-`) or quoted.
+/// - Values that are basic `"…"` strings with the `\n`, `\t`, `\r`, `\"`,
+///   `\\`, `\uXXXX` and `\UXXXXXXXX` escapes, literal `'…'` strings,
+///   unsigned integers, which may use `_` between digits, and `true` or
+///   `false`.
+/// - `#` comments, on their own line or after an item, and blank lines.
+///
+/// Anything else, such as arrays, inline tables, floats, dates, multi-line
+/// strings or dotted keys on the left of `=`, is an error naming its line.
+/// Unlike TOML, a key may be set again, and the last value is the one kept.
+pub fn parse(text: &str) -> Result<Vec<Entry>, String> {
+    let mut entries = Vec::new();
+    let mut table = String::new();
+    for (index, line) in text.lines().enumerate() {
+        let line_number = index + 1;
+        let error = |message: &str| format!("line {line_number}: {message}");
+        let rest = line.trim_start();
+        if rest.is_empty() || rest.starts_with('#') {
+            continue;
+        }
+        if let Some(header) = rest.strip_prefix('[') {
+            let (name, rest) = parse_dotted_key(header).map_err(|message| error(&message))?;
+            let rest = rest
+                .trim_start()
+                .strip_prefix(']')
+                .ok_or_else(|| error("expected `]`"))?;
+            expect_end(rest).map_err(|message| error(&message))?;
+            table = name;
+            continue;
+        }
+        let (key, rest) = parse_key(rest).map_err(|message| error(&message))?;
+        let rest = rest
+            .trim_start()
+            .strip_prefix('=')
+            .ok_or_else(|| error("expected `=`"))?;
+        let (value, rest) = parse_value(rest.trim_start()).map_err(|message| error(&message))?;
+        expect_end(rest).map_err(|message| error(&message))?;
+        entries.push(Entry {
+            line: line_number,
+            table: table.clone(),
+            key,
+            value,
+        });
+    }
+    Ok(entries)
+}
+
+fn parse_dotted_key(text: &str) -> Result<(String, &str), String> {
+    let (mut name, mut rest) = parse_key(text.trim_start())?;
+    while let Some(after) = rest.trim_start().strip_prefix('.') {
+        let (part, after) = parse_key(after.trim_start())?;
+        name.push('.');
+        name.push_str(&part);
+        rest = after;
+    }
+    Ok((name, rest))
+}
+
+fn parse_key(text: &str) -> Result<(String, &str), String> {
+    if text.starts_with(['"', '\'']) {
+        return parse_string(text);
+    }
+    let end = text
+        .find(|char: char| !(char.is_ascii_alphanumeric() || char == '_' || char == '-'))
+        .unwrap_or(text.len());
+    if end == 0 {
+        return Err(String::from("expected a key"));
+    }
+    Ok((String::from(&text[..end]), &text[end..]))
+}
+
+fn parse_value(text: &str) -> Result<(Value, &str), String> {
+    if text.starts_with(['"', '\'']) {
+        let (string, rest) = parse_string(text)?;
+        return Ok((Value::Text(string), rest));
+    }
+    let end = text
+        .find(|char: char| !(char.is_ascii_alphanumeric() || char == '_'))
+        .unwrap_or(text.len());
+    let value = match &text[..end] {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        word => Value::Number(
+            word.replace('_', "")
+                .parse()
+                .map_err(|_| format!("invalid value `{word}`"))?,
+        ),
+    };
+    Ok((value, &text[end..]))
+}
+
+/// Parses a basic `"…"` string with escapes, or a literal `'…'` string.
+fn parse_string(text: &str) -> Result<(String, &str), String> {
+    let mut chars = text.char_indices();
+    let quote = chars.next().map(|(_, quote)| quote);
+    let mut string = String::new();
+    while let Some((index, char)) = chars.next() {
+        match char {
+            _ if Some(char) == quote => return Ok((string, &text[index + 1..])),
+            '\\' if quote == Some('"') => {
+                let escaped = match chars.next().map(|(_, char)| char) {
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some(kind @ ('u' | 'U')) => {
+                        let digits: String = chars
+                            .by_ref()
+                            .take(if kind == 'u' { 4 } else { 8 })
+                            .map(|(_, char)| char)
+                            .collect();
+                        u32::from_str_radix(&digits, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| format!("invalid escape `\\{kind}{digits}`"))?
+                    }
+                    _ => return Err(String::from("invalid escape")),
+                };
+                string.push(escaped);
+            }
+            _ => string.push(char),
+        }
+    }
+    Err(String::from("unterminated string"))
+}
+
+fn expect_end(rest: &str) -> Result<(), String> {
+    let rest = rest.trim_start();
+    if rest.is_empty() || rest.starts_with('#') {
+        Ok(())
+    } else {
+        Err(format!("unexpected `{rest}`"))
+    }
+}
@@ -1,20 +1,98 @@
-use crossterm::cursor::{MoveLeft, RestorePosition, SavePosition, SetCursorStyle};
-use crossterm::event::{read, Event, KeyCode, KeyEvent, KeyModifiers};
-use crossterm::{execute, queue};
-use std::io::stdout;
+use std::time::Duration;
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use regex::{Regex, RegexBuilder};
+use unicode_segmentation::UnicodeSegmentation;
 
 use super::buffer::Buffer;
+use super::buffer_list::BufferList;
+use super::command::{self, Command, Substitution};
+use super::jump_list::{Jump, JumpList};
+use super::keymap::{Action, Keymap, MapMode, Step};
+use super::keys;
+use super::motion::{self, Find, Kind, Motion};
+use super::operator::{Operator, Range};
+use super::register::{self, Register, Registers};
+use super::repeat::Recorder;
+use super::selection::{Selection, Shape};
 use super::terminal::Terminal;
-use super::{screen, IOResult};
+use super::window_list::{Direction, Orientation, WindowList};
+use super::{display, screen, IOResult};
+
+/// How deeply macros may play other macros, which stops a macro that plays
+/// itself.
+const MAX_MACRO_DEPTH: usize = 100;
+
+enum Answer {
+    Yes,
+    No,
+    All,
+    Last,
+    Quit,
+}
 
+#[derive(Clone, Copy)]
 enum EditorMode {
     Insert,
     Control,
     Command,
+    OperatorPending {
+        operator: Operator,
+        count: Option<usize>,
+    },
+    Visual(Selection),
+}
+
+impl EditorMode {
+    fn name(&self) -> &'static str {
+        match self {
+            EditorMode::Insert => "INSERT",
+            EditorMode::Control => "CONTROL",
+            EditorMode::Command => "COMMAND",
+            EditorMode::OperatorPending { .. } => "PENDING",
+            EditorMode::Visual(selection) => match selection.shape {
+                Shape::Characters => "VISUAL",
+                Shape::Lines => "V-LINE",
+                Shape::Block => "V-BLOCK",
+            },
+        }
+    }
+}
+
+/// A blockwise `I` or `A` in progress. When Insert mode ends, the text typed
+/// on the top line is inserted at `column` of the other lines, padding short
+/// lines with spaces when appending.
+#[derive(Clone, Copy)]
+struct BlockInsert {
+    column: usize,
+    rows: (usize, usize),
+    line_len: usize,
+    append: bool,
+    to_line_end: bool,
 }
+
 pub struct Controller {
     should_quit: bool,
     command_text: String,
+    command_cursor: usize,
+    message: String,
+    search_pattern: Option<String>,
+    search_forward: bool,
+    window_prefix: bool,
+    pending_key: Option<char>,
+    count: Option<usize>,
+    last_find: Option<Find>,
+    registers: Registers,
+    register_name: Option<char>,
+    block_insert: Option<BlockInsert>,
+    insert_repeat: Option<(usize, Vec<KeyEvent>)>,
+    search_selection: Option<Selection>,
+    changes: Recorder,
+    recording: Option<(char, Vec<KeyEvent>)>,
+    last_macro: Option<char>,
+    macro_depth: usize,
+    jumps: JumpList,
+    keymap: Keymap,
     mode: EditorMode,
 }
 
@@ -23,6 +101,25 @@ impl Default for Controller {
         Controller {
             should_quit: false,
             command_text: String::new(),
+            command_cursor: 0,
+            message: String::new(),
+            search_pattern: None,
+            search_forward: true,
+            window_prefix: false,
+            pending_key: None,
+            count: None,
+            last_find: None,
+            registers: Registers::default(),
+            register_name: None,
+            block_insert: None,
+            insert_repeat: None,
+            search_selection: None,
+            changes: Recorder::default(),
+            recording: None,
+            last_macro: None,
+            macro_depth: 0,
+            jumps: JumpList::default(),
+            keymap: Keymap::default(),
             mode: EditorMode::Control,
         }
     }
@@ -33,62 +130,489 @@ impl Controller {
         Controller::default()
     }
 
-    fn set_mode(&mut self, mode: EditorMode) -> IOResult {
+    fn set_mode(&mut self, terminal: &mut Terminal, mode: EditorMode) -> IOResult {
         self.mode = mode;
-        match self.mode {
-            EditorMode::Insert => {
-                execute!(stdout(), SetCursorStyle::BlinkingBar)?;
-            }
+        let options = terminal.options();
+        let shape = match self.mode {
+            EditorMode::Insert => options.insert_cursor,
             EditorMode::Control => {
-                execute!(stdout(), SetCursorStyle::BlinkingBlock)?;
                 self.command_text.clear();
+                options.control_cursor
+            }
+            EditorMode::Visual(_) => options.visual_cursor,
+            EditorMode::Command => {
+                self.message.clear();
+                options.command_cursor
+            }
+            EditorMode::OperatorPending { .. } => options.pending_cursor,
+        };
+        terminal.set_cursor_style(shape.style(options.cursor_blink))
+    }
+
+    fn render(
+        &self,
+        buffers: &BufferList,
+        windows: &WindowList,
+        terminal: &mut Terminal,
+    ) -> IOResult {
+        let rect = windows.active_rect(screen::text_area(terminal)?);
+        terminal.set_view_size((rect.width, rect.height));
+        let (x, y) = terminal.virtual_cursor();
+        terminal.goto(buffers.current(), x, y)?;
+        let selection = match (self.mode, self.search_selection) {
+            (EditorMode::Visual(selection), _) | (EditorMode::Command, Some(selection)) => {
+                Some(selection.range(buffers.current(), (x, y)))
             }
+            _ => None,
+        };
+        let mode = match self.recording {
+            Some((name, _)) => format!("{} recording @{name}", self.mode.name()),
+            None => String::from(self.mode.name()),
+        };
+        match self.mode {
             EditorMode::Command => {
-                queue!(stdout(), SavePosition, SetCursorStyle::BlinkingBar)?;
-                screen::update_command_text(&self.command_text)?;
+                let cursor = display::width(&self.command_text[..self.command_cursor]);
+                screen::render(
+                    buffers,
+                    windows,
+                    terminal,
+                    &mode,
+                    selection,
+                    &self.command_text,
+                    Some(cursor),
+                )
             }
+            _ => screen::render(
+                buffers,
+                windows,
+                terminal,
+                &mode,
+                selection,
+                &self.message,
+                None,
+            ),
         }
-        Ok(())
     }
 
-    pub fn init(&mut self, buffer: &Buffer, terminal: &mut Terminal) -> IOResult {
-        terminal.goto(buffer, 0, 0)?;
-        screen::update_line_until_eof(buffer, terminal, 0)?;
-        Ok(())
+    pub fn set_message(&mut self, message: String) {
+        self.message = message;
+    }
+
+    pub fn init(
+        &mut self,
+        buffers: &BufferList,
+        windows: &WindowList,
+        terminal: &mut Terminal,
+    ) -> IOResult {
+        self.set_mode(terminal, EditorMode::Control)?;
+        terminal.goto(buffers.current(), 0, 0)?;
+        self.render(buffers, windows, terminal)
     }
 
-    pub fn handle_input(&mut self, buffer: &mut Buffer, terminal: &mut Terminal) -> IOResult {
+    pub fn handle_input(
+        &mut self,
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
+    ) -> IOResult {
         loop {
             if self.should_quit {
                 break;
             }
-            if let Event::Key(event) = read()? {
-                if KeyCode::Char('q') == event.code && event.modifiers == KeyModifiers::CONTROL {
-                    self.should_quit = true;
+            if self.keymap.is_pending() {
+                let timeout = Duration::from_millis(terminal.options().timeout_len as u64);
+                if !terminal.poll_event(timeout)? {
+                    self.handle_mapped_keys(buffers, windows, terminal, true)?;
+                    self.render(buffers, windows, terminal)?;
+                    continue;
                 }
-                if KeyCode::Char('s') == event.code && event.modifiers == KeyModifiers::CONTROL {
-                    buffer.write_file(None)?;
-                }
-                if KeyCode::Esc == event.code {
-                    self.set_mode(EditorMode::Control)?;
+            }
+            let Some(event) = terminal.read_event()? else {
+                break;
+            };
+            if let Event::Key(event) = event {
+                if let Some((_, keys)) = &mut self.recording {
+                    keys.push(event);
                 }
+                self.keymap.push(event);
+                self.handle_mapped_keys(buffers, windows, terminal, false)?;
+            }
+            self.render(buffers, windows, terminal)?;
+        }
+        Ok(())
+    }
+
+    pub fn map(&mut self, terminal: &Terminal, modes: &[MapMode], keys: &str, action: &str) {
+        self.keymap
+            .map(modes, keys, action, &terminal.options().leader);
+    }
+
+    /// The keymap the typed keys are looked up in, if any. Keys typed after
+    /// a key that waits for another, such as `f` or Ctrl-W, aren't mapped.
+    fn map_mode(&self) -> Option<MapMode> {
+        if self.pending_key.is_some() || self.window_prefix {
+            return None;
+        }
+        Some(match self.mode {
+            EditorMode::Control => MapMode::Control,
+            EditorMode::Insert => MapMode::Insert,
+            EditorMode::Visual(_) => MapMode::Visual,
+            EditorMode::OperatorPending { .. } => MapMode::OperatorPending,
+            EditorMode::Command => MapMode::Command,
+        })
+    }
 
-                match self.mode {
-                    EditorMode::Insert => self.handle_input_insert_mode(buffer, terminal, event)?,
-                    EditorMode::Control => {
-                        self.handle_input_control_mode(buffer, terminal, event)?
+    /// Handles the typed keys that are known not to start a longer mapping,
+    /// or all of them once `timed_out`.
+    fn handle_mapped_keys(
+        &mut self,
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
+        timed_out: bool,
+    ) -> IOResult {
+        while let Some(step) = self.keymap.next(self.map_mode(), timed_out) {
+            match step {
+                Step::Key(event) => self.handle_key(buffers, windows, terminal, event)?,
+                Step::Action(Action::Keys(keys)) => {
+                    for event in keys {
+                        self.handle_key(buffers, windows, terminal, event)?;
                     }
-                    EditorMode::Command => {
-                        self.handle_input_command_mode(buffer, terminal, event)?
+                }
+                Step::Action(Action::Command(text)) => {
+                    self.changes.discard();
+                    match command::parse(text) {
+                        Ok(command) => self.execute_command(buffers, windows, terminal, command)?,
+                        Err(message) => self.message = message,
                     }
                 }
             }
+            if self.should_quit {
+                break;
+            }
         }
         Ok(())
     }
 
-    fn handle_input_insert_mode(
+    /// Handles a key, recording it as part of the command being typed so
+    /// the last change can be repeated with `.`.
+    fn handle_key(
         &mut self,
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
+        event: KeyEvent,
+    ) -> IOResult {
+        self.move_jumps(buffers);
+        self.jumps.set_origin(Jump {
+            buffer: buffers.number(buffers.current_index()),
+            position: terminal.virtual_cursor(),
+        });
+        let repeat = matches!(self.mode, EditorMode::Control)
+            && self.pending_key.is_none()
+            && event.code == KeyCode::Char('.');
+        if repeat {
+            let count = self.count.take();
+            self.changes.discard();
+            return self.repeat_change(buffers, windows, terminal, count);
+        }
+        if matches!(self.mode, EditorMode::Control) && self.pending_key == Some('@') {
+            self.pending_key = None;
+            let count = self.count.take();
+            self.changes.discard();
+            return self.play_macro(buffers, windows, terminal, event.code, count);
+        }
+        if self.handle_jump_key(buffers, windows, terminal, event)? {
+            self.changes.discard();
+            return Ok(());
+        }
+        if matches!(self.mode, EditorMode::Command) {
+            self.changes.mark_command_line();
+        }
+        let count = self.count;
+        let changes = buffers.current().changes();
+        self.dispatch_key(buffers, windows, terminal, event)?;
+        match event.code {
+            KeyCode::Char(digit @ '0'..='9') if self.count.is_some() && self.count != count => {
+                let digit = digit.to_digit(10).map_or(0, |digit| digit as usize);
+                self.changes.record_count_digit(digit);
+            }
+            _ => self.changes.record(event),
+        }
+        if buffers.current().changes() != changes {
+            self.changes.mark_changed();
+        }
+        if self.is_idle() {
+            self.changes.finish();
+        }
+        Ok(())
+    }
+
+    /// Whether no command is partly typed.
+    fn is_idle(&self) -> bool {
+        matches!(self.mode, EditorMode::Control)
+            && self.pending_key.is_none()
+            && self.count.is_none()
+            && self.register_name.is_none()
+            && !self.window_prefix
+    }
+
+    fn repeat_change(
+        &mut self,
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
+        count: Option<usize>,
+    ) -> IOResult {
+        let Some(change) = self.changes.last() else {
+            return Ok(());
+        };
+        let keys = change.keys(count);
+        if let Some(count) = count {
+            self.changes.set_count(count);
+        }
+        for event in keys {
+            self.dispatch_key(buffers, windows, terminal, event)?;
+        }
+        Ok(())
+    }
+
+    fn play_macro(
+        &mut self,
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
+        code: KeyCode,
+        count: Option<usize>,
+    ) -> IOResult {
+        let name = match code {
+            KeyCode::Char('@') => self.last_macro,
+            KeyCode::Char(name) if register::is_valid(name) => Some(name.to_ascii_lowercase()),
+            _ => return Ok(()),
+        };
+        let Some(name) = name else {
+            self.message = String::from("No previously used register");
+            return Ok(());
+        };
+        let Some(register) = self.registers.get(Some(name)) else {
+            self.message = format!("Nothing in register {name}");
+            return Ok(());
+        };
+        if self.macro_depth >= MAX_MACRO_DEPTH {
+            return Ok(());
+        }
+        self.last_macro = Some(name);
+        let keys = keys::parse(&register.text);
+        self.macro_depth += 1;
+        let pending = self.keymap.take_pending();
+        'play: for _ in 0..count.unwrap_or(1) {
+            for event in &keys {
+                self.keymap.push(*event);
+                self.handle_mapped_keys(buffers, windows, terminal, false)?;
+                if self.should_quit {
+                    break 'play;
+                }
+            }
+        }
+        self.handle_mapped_keys(buffers, windows, terminal, true)?;
+        self.keymap.restore_pending(pending);
+        self.macro_depth -= 1;
+        Ok(())
+    }
+
+    /// Handles the Control mode keys that can move to another buffer:
+    /// setting and jumping to marks, and walking the jump list. Returns
+    /// whether `event` was one of them.
+    fn handle_jump_key(
+        &mut self,
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
+        event: KeyEvent,
+    ) -> Result<bool, std::io::Error> {
+        if !matches!(self.mode, EditorMode::Control) {
+            return Ok(false);
+        }
+        let code = match event.code {
+            KeyCode::Char('i') if event.modifiers == KeyModifiers::CONTROL => KeyCode::Tab,
+            code => code,
+        };
+        let control = event.modifiers == KeyModifiers::CONTROL;
+        let count = self.count.unwrap_or(1);
+        match (self.pending_key, code) {
+            (Some('m'), KeyCode::Char(name @ 'a'..='z')) => {
+                buffers
+                    .current_mut()
+                    .set_mark(name, terminal.virtual_cursor());
+            }
+            (Some('m'), KeyCode::Char(name @ 'A'..='Z')) => {
+                buffers.set_global_mark(name, terminal.virtual_cursor());
+            }
+            (Some(prefix @ ('`' | '\'')), KeyCode::Char(name)) => {
+                self.pending_key = None;
+                self.jump_to_mark(buffers, windows, terminal, name, prefix == '\'')?;
+            }
+            (None, KeyCode::Char('o')) if control => {
+                let current = Jump {
+                    buffer: buffers.number(buffers.current_index()),
+                    position: terminal.virtual_cursor(),
+                };
+                if let Some(jump) = self.jumps.back(current, count) {
+                    self.go_to_jump(buffers, windows, terminal, jump)?;
+                }
+            }
+            (None, KeyCode::Tab) => {
+                if let Some(jump) = self.jumps.forward(count) {
+                    self.go_to_jump(buffers, windows, terminal, jump)?;
+                }
+            }
+            _ => return Ok(false),
+        }
+        self.pending_key = None;
+        self.count = None;
+        Ok(true)
+    }
+
+    fn jump_to_mark(
+        &mut self,
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
+        name: char,
+        linewise: bool,
+    ) -> IOResult {
+        let index = if name.is_ascii_uppercase() {
+            buffers.index_of_mark(name)
+        } else {
+            buffers
+                .current()
+                .mark(name)
+                .map(|_| buffers.current_index())
+        };
+        let Some(index) = index else {
+            self.message = String::from("Mark not set");
+            return Ok(());
+        };
+        if index != buffers.current_index() {
+            self.switch_buffer(buffers, windows, terminal, index)?;
+        }
+        self.move_cursor(
+            buffers.current(),
+            terminal,
+            Motion::Mark { name, linewise },
+            None,
+        )
+    }
+
+    /// Moves the jumps in the current buffer with the lines inserted and
+    /// deleted in it since the last time.
+    fn move_jumps(&mut self, buffers: &mut BufferList) {
+        let number = buffers.number(buffers.current_index());
+        for edit in buffers.current_mut().take_line_edits() {
+            self.jumps.move_rows(number, edit);
+        }
+    }
+
+    fn go_to_jump(
+        &mut self,
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
+        jump: Jump,
+    ) -> IOResult {
+        let Some(index) = buffers.index_of_number(jump.buffer) else {
+            return Ok(());
+        };
+        if index != buffers.current_index() {
+            self.switch_buffer(buffers, windows, terminal, index)?;
+        }
+        let (x, y) = jump.position;
+        Self::restore_cursor(buffers.current(), terminal, x, y)
+    }
+
+    fn stop_recording(&mut self) {
+        let Some((name, mut keys)) = self.recording.take() else {
+            return;
+        };
+        keys.pop();
+        let register = Register {
+            text: keys::to_notation(&keys),
+            shape: Shape::Characters,
+        };
+        self.registers.set(name, register);
+    }
+
+    fn dispatch_key(
+        &mut self,
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
+        event: KeyEvent,
+    ) -> IOResult {
+        if self.message.contains('\n') {
+            self.message.clear();
+        }
+        if matches!(self.mode, EditorMode::Control) {
+            if self.window_prefix {
+                self.window_prefix = false;
+                return self.window_command(buffers, windows, terminal, event.code);
+            }
+            if KeyCode::Char('w') == event.code && event.modifiers == KeyModifiers::CONTROL {
+                self.window_prefix = true;
+                return Ok(());
+            }
+        }
+        if let Some((_times, keys)) = &mut self.insert_repeat {
+            if KeyCode::Esc != event.code {
+                keys.push(event);
+            }
+        }
+        if KeyCode::Esc == event.code {
+            self.count = None;
+            self.register_name = None;
+            if let Some((times, keys)) = self.insert_repeat.take() {
+                for _ in 0..times {
+                    for event in &keys {
+                        Self::handle_input_insert_mode(buffers.current_mut(), terminal, *event)?;
+                    }
+                }
+            }
+            if let Some(block) = self.block_insert.take() {
+                Self::finish_block_insert(buffers.current_mut(), terminal, block)?;
+            }
+            buffers.current_mut().end_undo_group();
+            if matches!(self.mode, EditorMode::Command) {
+                self.leave_command_line(terminal)?;
+            } else {
+                self.set_mode(terminal, EditorMode::Control)?;
+            }
+        }
+
+        match self.mode {
+            EditorMode::Insert => {
+                Self::handle_input_insert_mode(buffers.current_mut(), terminal, event)
+            }
+            EditorMode::Control => {
+                self.handle_input_control_mode(buffers.current_mut(), terminal, event)
+            }
+            EditorMode::Command => {
+                self.handle_input_command_mode(buffers, windows, terminal, event)
+            }
+            EditorMode::OperatorPending { operator, count } => self
+                .handle_input_operator_pending_mode(
+                    buffers.current_mut(),
+                    terminal,
+                    event,
+                    operator,
+                    count,
+                ),
+            EditorMode::Visual(selection) => {
+                self.handle_input_visual_mode(buffers.current_mut(), terminal, event, selection)
+            }
+        }
+    }
+
+    fn handle_input_insert_mode(
         buffer: &mut Buffer,
         terminal: &mut Terminal,
         event: KeyEvent,
@@ -96,42 +620,45 @@ impl Controller {
         let (x, y) = terminal.virtual_cursor();
         match event.code {
             KeyCode::Char(char) => buffer.insert_char_on_line(terminal, char, y, x)?,
+            KeyCode::Tab if terminal.options().expand_tab => {
+                let line = buffer.get_line(y).unwrap_or_default();
+                let tab_stop = terminal.options().tab_stop;
+                let spaces = display::tab_width(display::width_until(&line, x, tab_stop), tab_stop);
+                for offset in 0..spaces {
+                    buffer.insert_char_on_line(terminal, ' ', y, x + offset)?;
+                }
+            }
+            KeyCode::Tab => buffer.insert_char_on_line(terminal, '\t', y, x)?,
             KeyCode::Delete => {
-                let line = buffer.get_line(y).unwrap();
-                if x >= line.len() && y == buffer.len() {
+                let line_len = buffer.line_len(y);
+                if x >= line_len && y + 1 == buffer.len() {
                     return Ok(());
                 }
-                if x >= line.len() {
+                if x >= line_len {
                     buffer.move_line_contents_up_one_row(y + 1)?;
                     buffer.delete_line(y + 1)?;
-                    screen::update_line_until_eof(buffer, terminal, y)?;
                 } else {
-                    buffer.delete_char_on_line(terminal, y, x)?;
+                    buffer.delete_char_on_line(y, x)?;
                 }
-                ()
             }
             KeyCode::Backspace => {
                 if x < 1 {
                     if y == 0 {
                         return Ok(());
                     }
-                    terminal.goto(buffer, buffer.get_line(y - 1).unwrap().len(), y)?;
+                    terminal.goto(buffer, buffer.line_len(y - 1), y)?;
                     buffer.move_line_contents_up_one_row(y)?;
                     buffer.delete_line(y)?;
                     terminal.move_up(buffer)?;
-                    screen::update_line_until_eof(buffer, terminal, y - 1)?;
                 } else {
                     terminal.move_left(buffer)?;
-                    buffer.delete_char_on_line(terminal, y, x - 1)?;
+                    buffer.delete_char_on_line(y, x - 1)?;
                 }
             }
             KeyCode::Enter => {
-                let line = buffer.get_line_mut(y).unwrap();
-                let append_line = line.split_off(x);
-                buffer.insert_line(y + 1, append_line)?;
+                buffer.split_line(y, x)?;
                 terminal.move_down(buffer)?;
                 terminal.goto_beginning_of_line(buffer)?;
-                screen::update_line_until_eof(buffer, terminal, y)?;
             }
             KeyCode::Down => terminal.move_down(buffer)?,
             KeyCode::Up => terminal.move_up(buffer)?,
@@ -148,89 +675,1297 @@ impl Controller {
         terminal: &mut Terminal,
         event: KeyEvent,
     ) -> IOResult {
-        match event.code {
-            KeyCode::Char(char) => match char {
-                'h' => {
-                    terminal.move_left(buffer)?;
-                }
-                'j' => {
-                    terminal.move_down(buffer)?;
-                }
-                'k' => {
-                    terminal.move_up(buffer)?;
-                }
-                'l' => {
-                    terminal.move_right(buffer)?;
+        if event.modifiers == KeyModifiers::CONTROL {
+            self.count = None;
+            match event.code {
+                KeyCode::Char('r') => self.redo(buffer, terminal)?,
+                KeyCode::Char('v') => {
+                    let selection = Selection::new(Shape::Block, terminal.virtual_cursor());
+                    self.set_mode(terminal, EditorMode::Visual(selection))?;
                 }
-                'i' => {
-                    self.set_mode(EditorMode::Insert)?;
+                _ => (),
+            }
+            return Ok(());
+        }
+        if let Some(prefix) = self.pending_key.take() {
+            let count = self.count.take();
+            return self.handle_pending_key(buffer, terminal, prefix, event.code, count);
+        }
+        if self.push_count_digit(event.code) {
+            return Ok(());
+        }
+        let count = self.count.take();
+        if let Some(motion) = self.motion(event.code) {
+            return self.move_cursor(buffer, terminal, motion, count);
+        }
+        let KeyCode::Char(char) = event.code else {
+            return Ok(());
+        };
+        if let Some(operator) = Operator::from_char(char) {
+            return self.set_mode(terminal, EditorMode::OperatorPending { operator, count });
+        }
+        match char {
+            'q' if self.recording.is_some() => self.stop_recording(),
+            'g' | 'f' | 'F' | 't' | 'T' | '"' | 'q' | '@' | 'm' | '`' | '\'' => {
+                self.pending_key = Some(char);
+                self.count = count;
+            }
+            'p' | 'P' => {
+                self.put(buffer, terminal, char == 'P', count)?;
+            }
+            'v' | 'V' => {
+                let shape = if char == 'v' {
+                    Shape::Characters
+                } else {
+                    Shape::Lines
+                };
+                let selection = Selection::new(shape, terminal.virtual_cursor());
+                self.set_mode(terminal, EditorMode::Visual(selection))?;
+            }
+            'i' => {
+                self.enter_insert_mode(buffer, terminal)?;
+                self.repeat_insert(count);
+            }
+            'a' => {
+                terminal.move_right_for_insert(buffer)?;
+                self.enter_insert_mode(buffer, terminal)?;
+                self.repeat_insert(count);
+            }
+            'u' => {
+                self.undo(buffer, terminal)?;
+            }
+            'n' => {
+                self.search_next(buffer, terminal, self.search_forward)?;
+            }
+            'N' => {
+                self.search_next(buffer, terminal, !self.search_forward)?;
+            }
+            ':' | '\\' | '/' | '?' => {
+                self.command_text = char.to_string();
+                self.command_cursor = self.command_text.len();
+                self.set_mode(terminal, EditorMode::Command)?;
+            }
+
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn motion(&self, code: KeyCode) -> Option<Motion> {
+        let KeyCode::Char(char) = code else {
+            return match code {
+                KeyCode::Left => Some(Motion::Left),
+                KeyCode::Down => Some(Motion::Down),
+                KeyCode::Up => Some(Motion::Up),
+                KeyCode::Right => Some(Motion::Right),
+                KeyCode::Home => Some(Motion::LineStart),
+                KeyCode::End => Some(Motion::LineEnd),
+                _ => None,
+            };
+        };
+        let motion = match char {
+            'h' => Motion::Left,
+            'j' => Motion::Down,
+            'k' => Motion::Up,
+            'l' | ' ' => Motion::Right,
+            'w' | 'W' => Motion::WordForward { big: char == 'W' },
+            'b' | 'B' => Motion::WordBackward { big: char == 'B' },
+            'e' | 'E' => Motion::WordEnd { big: char == 'E' },
+            '0' => Motion::LineStart,
+            '^' => Motion::FirstNonBlank,
+            '$' => Motion::LineEnd,
+            'G' => Motion::LastLine,
+            '}' => Motion::ParagraphForward,
+            '{' => Motion::ParagraphBackward,
+            ';' => Motion::Find(self.last_find?),
+            ',' => Motion::Find(self.last_find?.reversed()),
+            'H' => Motion::ScreenTop,
+            'M' => Motion::ScreenMiddle,
+            'L' => Motion::ScreenBottom,
+            '%' => Motion::MatchingBracket,
+            _ => return None,
+        };
+        Some(motion)
+    }
+
+    fn move_cursor(
+        &mut self,
+        buffer: &Buffer,
+        terminal: &mut Terminal,
+        motion: Motion,
+        count: Option<usize>,
+    ) -> IOResult {
+        let viewport = terminal.viewport(buffer)?;
+        let Some((x, y)) = motion.target(buffer, terminal.virtual_cursor(), count, viewport) else {
+            return Ok(());
+        };
+        if motion.is_jump() {
+            self.jumps.record();
+        }
+        let x = usize::min(x, usize::max(buffer.line_len(y), 1) - 1);
+        terminal.goto(buffer, x, y)
+    }
+
+    /// Adds a typed digit to the count, returning whether the key was one.
+    /// A leading `0` is the `LineStart` motion rather than part of a count.
+    fn push_count_digit(&mut self, code: KeyCode) -> bool {
+        let KeyCode::Char(digit @ '0'..='9') = code else {
+            return false;
+        };
+        if digit == '0' && self.count.is_none() {
+            return false;
+        }
+        let digit = digit.to_digit(10).map_or(0, |digit| digit as usize);
+        self.count = Some(self.count.unwrap_or(0).saturating_mul(10) + digit);
+        true
+    }
+
+    /// The motion completed by the key typed after `prefix`.
+    fn pending_motion(&mut self, prefix: char, code: KeyCode) -> Option<Motion> {
+        match (prefix, code) {
+            ('g', KeyCode::Char('g')) => Some(Motion::FirstLine),
+            ('f' | 'F' | 't' | 'T', KeyCode::Char(char)) => {
+                let find = Find {
+                    char,
+                    forward: prefix.is_ascii_lowercase(),
+                    till: prefix.eq_ignore_ascii_case(&'t'),
+                };
+                self.last_find = Some(find);
+                Some(Motion::Find(find))
+            }
+            ('`' | '\'', KeyCode::Char(name)) => Some(Motion::Mark {
+                name,
+                linewise: prefix == '\'',
+            }),
+            _ => None,
+        }
+    }
+
+    fn handle_pending_key(
+        &mut self,
+        buffer: &mut Buffer,
+        terminal: &mut Terminal,
+        prefix: char,
+        code: KeyCode,
+        count: Option<usize>,
+    ) -> IOResult {
+        if let Some(motion) = self.pending_motion(prefix, code) {
+            return self.move_cursor(buffer, terminal, motion, count);
+        }
+        let case_operator = match (prefix, code) {
+            ('g', KeyCode::Char(char)) => Operator::from_case_char(char),
+            _ => None,
+        };
+        if let Some(operator) = case_operator {
+            return match self.mode {
+                EditorMode::Visual(selection) => {
+                    self.apply_selection(buffer, terminal, selection, operator)
                 }
-                'a' => {
-                    terminal.move_right_for_insert(buffer)?;
-                    self.set_mode(EditorMode::Insert)?;
+                _ => self.set_mode(terminal, EditorMode::OperatorPending { operator, count }),
+            };
+        }
+        match (prefix, code) {
+            ('"', KeyCode::Char(name)) if register::is_valid(name) => {
+                self.register_name = Some(name);
+                self.count = count;
+                Ok(())
+            }
+            ('q', KeyCode::Char(name)) if register::is_valid(name) => {
+                self.recording = Some((name, Vec::new()));
+                Ok(())
+            }
+            ('g', KeyCode::Char('j') | KeyCode::Down) => {
+                for _ in 0..count.unwrap_or(1) {
+                    terminal.move_screen_row(buffer, true)?;
                 }
-                ':' | '\\' => {
-                    self.command_text.clear();
-                    self.command_text.insert(0, char);
-                    self.set_mode(EditorMode::Command)?;
+                Ok(())
+            }
+            ('g', KeyCode::Char('k') | KeyCode::Up) => {
+                for _ in 0..count.unwrap_or(1) {
+                    terminal.move_screen_row(buffer, false)?;
                 }
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
 
-                _ => (),
-            },
-            KeyCode::Down => terminal.move_down(buffer)?,
-            KeyCode::Up => terminal.move_up(buffer)?,
-            KeyCode::Left => terminal.move_left(buffer)?,
-            KeyCode::Right => terminal.move_right_for_insert(buffer)?,
+    fn handle_input_operator_pending_mode(
+        &mut self,
+        buffer: &mut Buffer,
+        terminal: &mut Terminal,
+        event: KeyEvent,
+        operator: Operator,
+        operator_count: Option<usize>,
+    ) -> IOResult {
+        if let Some(prefix) = self.pending_key.take() {
+            let count = multiply_counts(operator_count, self.count.take());
+            self.set_mode(terminal, EditorMode::Control)?;
+            return match self.pending_motion(prefix, event.code) {
+                Some(motion) => self.apply_operator(buffer, terminal, operator, motion, count),
+                None => Ok(()),
+            };
+        }
+        if self.push_count_digit(event.code) {
+            return Ok(());
+        }
+        if let KeyCode::Char(char @ ('g' | 'f' | 'F' | 't' | 'T' | '`' | '\'')) = event.code {
+            self.pending_key = Some(char);
+            return Ok(());
+        }
+        let count = multiply_counts(operator_count, self.count.take());
+        self.set_mode(terminal, EditorMode::Control)?;
+        if event.code == KeyCode::Char(operator.char()) {
+            let (_x, y) = terminal.virtual_cursor();
+            let end = usize::min(y + count.unwrap_or(1) - 1, buffer.len() - 1);
+            return self.apply_range(buffer, terminal, operator, Range::Lines(y, end));
+        }
+        match self.motion(event.code) {
+            Some(motion) => self.apply_operator(buffer, terminal, operator, motion, count),
+            None => Ok(()),
+        }
+    }
+
+    fn handle_input_visual_mode(
+        &mut self,
+        buffer: &mut Buffer,
+        terminal: &mut Terminal,
+        event: KeyEvent,
+        selection: Selection,
+    ) -> IOResult {
+        if event.modifiers == KeyModifiers::CONTROL {
+            self.count = None;
+            if event.code == KeyCode::Char('v') {
+                return self.change_selection_shape(terminal, selection, Shape::Block);
+            }
+            return Ok(());
+        }
+        if let Some(prefix) = self.pending_key.take() {
+            let count = self.count.take();
+            return self.handle_pending_key(buffer, terminal, prefix, event.code, count);
+        }
+        if self.push_count_digit(event.code) {
+            return Ok(());
+        }
+        let count = self.count.take();
+        if let Some(motion) = self.motion(event.code) {
+            let to_line_end = motion == Motion::LineEnd
+                || (selection.to_line_end && matches!(motion, Motion::Up | Motion::Down));
+            self.mode = EditorMode::Visual(Selection {
+                to_line_end,
+                ..selection
+            });
+            return self.move_cursor(buffer, terminal, motion, count);
+        }
+        let char = match event.code {
+            KeyCode::Char(char) => char,
+            KeyCode::Delete => 'x',
+            _ => return Ok(()),
+        };
+        let operator = match char {
+            'x' => Some(Operator::Delete),
+            's' => Some(Operator::Change),
+            _ => Operator::from_char(char).or_else(|| Operator::from_case_char(char)),
+        };
+        if let Some(operator) = operator {
+            return self.apply_selection(buffer, terminal, selection, operator);
+        }
+        match char {
+            'g' | 'f' | 'F' | 't' | 'T' | '"' | '`' | '\'' => {
+                self.pending_key = Some(char);
+                self.count = count;
+            }
+            'v' => self.change_selection_shape(terminal, selection, Shape::Characters)?,
+            'V' => self.change_selection_shape(terminal, selection, Shape::Lines)?,
+            'o' => {
+                let (x, y) = selection.anchor;
+                self.mode = EditorMode::Visual(Selection {
+                    anchor: terminal.virtual_cursor(),
+                    ..selection
+                });
+                terminal.goto(buffer, x, y)?;
+            }
+            'I' | 'A' if selection.shape == Shape::Block => {
+                let range = selection.range(buffer, terminal.virtual_cursor());
+                self.enter_insert_mode(buffer, terminal)?;
+                self.begin_block_insert(buffer, terminal, range, char == 'A')?;
+            }
+            'n' => {
+                self.search_next(buffer, terminal, self.search_forward)?;
+            }
+            'N' => {
+                self.search_next(buffer, terminal, !self.search_forward)?;
+            }
+            '/' | '?' => {
+                self.search_selection = Some(selection);
+                self.command_text = char.to_string();
+                self.command_cursor = self.command_text.len();
+                self.set_mode(terminal, EditorMode::Command)?;
+            }
             _ => (),
         }
         Ok(())
     }
 
-    fn handle_input_command_mode(
+    /// Switches the selection to `shape`, or leaves Visual mode if it
+    /// already has that shape.
+    fn change_selection_shape(
+        &mut self,
+        terminal: &mut Terminal,
+        selection: Selection,
+        shape: Shape,
+    ) -> IOResult {
+        if selection.shape == shape {
+            return self.set_mode(terminal, EditorMode::Control);
+        }
+        self.mode = EditorMode::Visual(Selection { shape, ..selection });
+        Ok(())
+    }
+
+    fn apply_selection(
         &mut self,
         buffer: &mut Buffer,
         terminal: &mut Terminal,
+        selection: Selection,
+        operator: Operator,
+    ) -> IOResult {
+        let range = selection.range(buffer, terminal.virtual_cursor());
+        self.set_mode(terminal, EditorMode::Control)?;
+        if operator == Operator::Change && selection.shape == Shape::Block {
+            self.enter_insert_mode(buffer, terminal)?;
+            self.apply_range(buffer, terminal, Operator::Delete, range)?;
+            return self.begin_block_insert(buffer, terminal, range, false);
+        }
+        self.apply_range(buffer, terminal, operator, range)
+    }
+
+    /// Moves to the left edge of a block, or just past its right edge when
+    /// appending, ready for Insert mode text that `finish_block_insert`
+    /// then repeats on every line of the block.
+    fn begin_block_insert(
+        &mut self,
+        buffer: &mut Buffer,
+        terminal: &mut Terminal,
+        range: Range,
+        append: bool,
+    ) -> IOResult {
+        let Range::Block((left, top), (right, bottom)) = range else {
+            return Ok(());
+        };
+        let to_line_end = append && right == usize::MAX;
+        let line_len = buffer.line_len(top);
+        let column = match (append, to_line_end) {
+            (false, _) => left,
+            (true, true) => line_len,
+            (true, false) => right,
+        };
+        if column > line_len {
+            buffer.insert_text((line_len, top), &" ".repeat(column - line_len))?;
+        }
+        self.block_insert = Some(BlockInsert {
+            column,
+            rows: (top, bottom),
+            line_len: buffer.line_len(top),
+            append,
+            to_line_end,
+        });
+        terminal.goto(buffer, column, top)
+    }
+
+    fn finish_block_insert(
+        buffer: &mut Buffer,
+        terminal: &mut Terminal,
+        block: BlockInsert,
+    ) -> IOResult {
+        let (x, y) = terminal.virtual_cursor();
+        let (top, bottom) = block.rows;
+        let inserted = buffer.line_len(top).saturating_sub(block.line_len);
+        if y != top || x < block.column || inserted == 0 {
+            return Ok(());
+        }
+        let text = buffer.text_range((block.column, top), (block.column + inserted, top));
+        for row in top + 1..=bottom {
+            let line_len = buffer.line_len(row);
+            let column = if block.to_line_end {
+                line_len
+            } else {
+                block.column
+            };
+            if line_len < column {
+                if !block.append {
+                    continue;
+                }
+                buffer.insert_text((line_len, row), &" ".repeat(column - line_len))?;
+            }
+            buffer.insert_text((column, row), &text)?;
+        }
+        Self::restore_cursor(buffer, terminal, block.column, top)
+    }
+
+    fn apply_operator(
+        &mut self,
+        buffer: &mut Buffer,
+        terminal: &mut Terminal,
+        operator: Operator,
+        motion: Motion,
+        count: Option<usize>,
+    ) -> IOResult {
+        let cursor = terminal.virtual_cursor();
+        let word = match motion {
+            Motion::WordForward { big } => {
+                let count = count.unwrap_or(1);
+                let change_end = (operator == Operator::Change)
+                    .then(|| motion::change_word_end(buffer, cursor, big, count))
+                    .flatten();
+                Some(match change_end {
+                    Some(end) => (end, Kind::Inclusive),
+                    None => (
+                        motion::operator_word_forward(buffer, cursor, big, count),
+                        Kind::Exclusive,
+                    ),
+                })
+            }
+            _ => None,
+        };
+        let (target, kind) = if let Some(word) = word {
+            word
+        } else {
+            let viewport = terminal.viewport(buffer)?;
+            let Some(target) = motion.target(buffer, cursor, count, viewport) else {
+                return Ok(());
+            };
+            (target, motion.kind())
+        };
+        let range = Range::new(buffer, cursor, target, kind);
+        self.apply_range(buffer, terminal, operator, range)
+    }
+
+    fn apply_range(
+        &mut self,
+        buffer: &mut Buffer,
+        terminal: &mut Terminal,
+        operator: Operator,
+        range: Range,
+    ) -> IOResult {
+        if operator == Operator::Change {
+            self.enter_insert_mode(buffer, terminal)?;
+        }
+        let (register, (x, y)) =
+            operator.apply(buffer, range, terminal.virtual_cursor(), terminal.options())?;
+        if let Some(message) = operator.report(range, register.as_ref()) {
+            self.message = message;
+        }
+        if let Some(register) = register {
+            self.store_register(terminal, operator, register)?;
+        }
+        if operator == Operator::Change {
+            return terminal.goto(buffer, x, y);
+        }
+        Self::restore_cursor(buffer, terminal, x, y)
+    }
+
+    fn store_register(
+        &mut self,
+        terminal: &mut Terminal,
+        operator: Operator,
+        register: Register,
+    ) -> IOResult {
+        let name = self.register_name.take();
+        if name == Some(register::CLIPBOARD) || (name.is_none() && terminal.options().clipboard) {
+            terminal.set_clipboard(&register.text)?;
+            if name.is_none() {
+                self.registers.set(register::CLIPBOARD, register.clone());
+            }
+        }
+        if operator == Operator::Yank {
+            self.registers.yank(name, register);
+        } else {
+            self.registers.delete(name, register);
+        }
+        Ok(())
+    }
+
+    /// Puts the text of the selected register after the cursor, or before it
+    /// for `P`. Linewise text goes on new lines below or above, and a block
+    /// goes on the cursor line and the ones below, at the same column.
+    fn put(
+        &mut self,
+        buffer: &mut Buffer,
+        terminal: &mut Terminal,
+        before: bool,
+        count: Option<usize>,
+    ) -> IOResult {
+        let name = self.register_name.take();
+        let Some(register) = self.registers.get(name) else {
+            let name = name.unwrap_or(register::UNNAMED);
+            self.message = format!("Nothing in register {name}");
+            return Ok(());
+        };
+        let count = count.unwrap_or(1);
+        let (x, y) = terminal.virtual_cursor();
+        buffer.begin_undo_group();
+        if register.shape == Shape::Lines {
+            let row = if before { y } else { y + 1 };
+            let lines = register.lines();
+            for (offset, line) in lines.iter().cycle().take(lines.len() * count).enumerate() {
+                buffer.insert_line(row + offset, String::from(*line))?;
+            }
+            buffer.end_undo_group();
+            return terminal.goto(buffer, motion::first_non_blank(buffer, row), row);
+        }
+        let column = if before || buffer.line_len(y) == 0 {
+            x
+        } else {
+            x + 1
+        };
+        if register.shape == Shape::Block {
+            let rows: Vec<&str> = register.text.split('\n').collect();
+            Self::put_block(buffer, &rows, (column, y), count)?;
+            buffer.end_undo_group();
+            return terminal.goto(buffer, column, y);
+        }
+        let text = register.text.repeat(count);
+        let end = buffer.insert_text((column, y), &text)?;
+        buffer.end_undo_group();
+        if text.contains('\n') {
+            return terminal.goto(buffer, column, y);
+        }
+        Self::restore_cursor(buffer, terminal, end.0.saturating_sub(1), end.1)
+    }
+
+    /// Inserts each row of a block at `column` on successive lines, adding
+    /// lines and padding short ones with spaces. Rows are padded to the
+    /// block's width so text after the column stays aligned.
+    fn put_block(
+        buffer: &mut Buffer,
+        rows: &[&str],
+        (column, y): (usize, usize),
+        count: usize,
+    ) -> IOResult {
+        let row_width = |row: &str| row.graphemes(true).count();
+        let width = rows.iter().map(|row| row_width(row)).max().unwrap_or(0);
+        for (offset, row_text) in rows.iter().enumerate() {
+            let row = y + offset;
+            if row >= buffer.len() {
+                buffer.insert_line(row, String::new())?;
+            }
+            let line_len = buffer.line_len(row);
+            if line_len < column {
+                buffer.insert_text((line_len, row), &" ".repeat(column - line_len))?;
+            }
+            let padding = width - row_width(row_text);
+            let mut text = format!("{row_text}{}", " ".repeat(padding)).repeat(count);
+            if line_len <= column {
+                text.truncate(text.len() - padding);
+            }
+            buffer.insert_text((column, row), &text)?;
+        }
+        Ok(())
+    }
+
+    fn handle_input_command_mode(
+        &mut self,
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
         event: KeyEvent,
     ) -> IOResult {
         match event.code {
             KeyCode::Char(char) => {
-                self.command_text.insert(self.command_text.len(), char);
-                screen::update_command_text(&self.command_text)?;
+                self.command_text.insert(self.command_cursor, char);
+                self.command_cursor += char.len_utf8();
             }
             KeyCode::Delete => {
-                let (x, _y) = Terminal::cursor_position()?;
-                if (x) < self.command_text.len() {
-                    self.command_text.remove(x);
-                    screen::update_command_text(&self.command_text)?;
-                }
-                if self.command_text.len() == 0 {
-                    self.exit_command_mode(buffer, terminal)?;
-                }
+                let end = self.next_command_boundary();
+                self.command_text
+                    .replace_range(self.command_cursor..end, "");
             }
             KeyCode::Backspace => {
-                let (x, _y) = Terminal::cursor_position()?;
-                if x as i32 - 1 < 0 {
+                let start = self.previous_command_boundary();
+                if start == 0 {
+                    if self.command_text.len() == self.command_cursor {
+                        self.leave_command_line(terminal)?;
+                    }
                     return Ok(());
                 }
-                queue!(stdout(), MoveLeft(1))?;
-                self.command_text.remove(x - 1);
-                screen::update_command_text(&self.command_text)?;
-                if self.command_text.len() == 0 {
-                    self.exit_command_mode(buffer, terminal)?;
+                self.command_text
+                    .replace_range(start..self.command_cursor, "");
+                self.command_cursor = start;
+            }
+            KeyCode::Left => {
+                let start = self.previous_command_boundary();
+                if start > 0 {
+                    self.command_cursor = start;
                 }
             }
+            KeyCode::Right => self.command_cursor = self.next_command_boundary(),
+            KeyCode::Home => self.command_cursor = 1,
+            KeyCode::End => self.command_cursor = self.command_text.len(),
             KeyCode::Enter => {
-                //EXECUTE
+                let command_text = self.command_text.clone();
+                self.leave_command_line(terminal)?;
+                if let Some(text) = command_text.strip_prefix([':', '\\']) {
+                    match command::parse(text) {
+                        Ok(command) => self.execute_command(buffers, windows, terminal, command)?,
+                        Err(message) => self.message = message,
+                    }
+                } else if let Some(pattern) = command_text.strip_prefix('/') {
+                    self.search(buffers.current(), terminal, pattern, true)?;
+                } else if let Some(pattern) = command_text.strip_prefix('?') {
+                    self.search(buffers.current(), terminal, pattern, false)?;
+                }
             }
             _ => (),
         }
         Ok(())
     }
 
-    fn exit_command_mode(&mut self, buffer: &Buffer, terminal: &Terminal) -> IOResult {
-        self.set_mode(EditorMode::Control)?;
-        execute!(stdout(), RestorePosition, SetCursorStyle::BlinkingBlock)?;
-        screen::update_line(buffer, terminal, Terminal::size()?.1 as usize)?;
+    /// Goes back to Control mode, or to the Visual mode a search was typed
+    /// in.
+    fn leave_command_line(&mut self, terminal: &mut Terminal) -> IOResult {
+        self.command_text.clear();
+        let mode = self
+            .search_selection
+            .take()
+            .map_or(EditorMode::Control, EditorMode::Visual);
+        self.set_mode(terminal, mode)
+    }
+
+    fn search(
+        &mut self,
+        buffer: &Buffer,
+        terminal: &mut Terminal,
+        pattern: &str,
+        forward: bool,
+    ) -> IOResult {
+        if !pattern.is_empty() {
+            self.search_pattern = Some(String::from(pattern));
+        }
+        self.search_forward = forward;
+        self.search_next(buffer, terminal, forward)
+    }
+
+    fn search_next(&mut self, buffer: &Buffer, terminal: &mut Terminal, forward: bool) -> IOResult {
+        let Some(pattern) = &self.search_pattern else {
+            self.message = String::from("No previous regular expression");
+            return Ok(());
+        };
+        let regex = match Self::compile(pattern, false) {
+            Ok(regex) => regex,
+            Err(message) => {
+                self.message = message;
+                return Ok(());
+            }
+        };
+        let (x, y) = terminal.virtual_cursor();
+        let Some((found_x, found_y)) = buffer.find(&regex, x, y, forward) else {
+            self.message = format!("Pattern not found: {pattern}");
+            return Ok(());
+        };
+        self.jumps.record();
+        self.message = if forward && (found_y, found_x) <= (y, x) {
+            String::from("search hit BOTTOM, continuing at TOP")
+        } else if !forward && (found_y, found_x) >= (y, x) {
+            String::from("search hit TOP, continuing at BOTTOM")
+        } else {
+            format!("{}{pattern}", if forward { '/' } else { '?' })
+        };
+        terminal.goto(buffer, found_x, found_y)
+    }
+
+    fn compile(pattern: &str, ignore_case: bool) -> Result<Regex, String> {
+        RegexBuilder::new(pattern)
+            .case_insensitive(ignore_case)
+            .build()
+            .map_err(|_| format!("Invalid pattern: {pattern}"))
+    }
+
+    fn substitute(
+        &mut self,
+        buffers: &mut BufferList,
+        windows: &WindowList,
+        terminal: &mut Terminal,
+        substitution: &Substitution,
+    ) -> IOResult {
+        let (regex, first, last) = match self.substitution_target(
+            buffers.current(),
+            terminal.virtual_cursor().1,
+            substitution,
+        ) {
+            Ok(target) => target,
+            Err(message) => {
+                self.message = message;
+                return Ok(());
+            }
+        };
+        let mut matched = false;
+        let mut confirm = substitution.confirm;
+        let mut count = 0;
+        let mut lines = 0;
+        let mut last_line = None;
+        buffers.current_mut().begin_undo_group();
+        'rows: for row in first..=last {
+            let mut start = 0;
+            let mut replaced_on_line = false;
+            loop {
+                let line = buffers
+                    .current()
+                    .get_line(row)
+                    .unwrap_or_default()
+                    .into_owned();
+                let Some(captures) = regex.captures_at(&line, start) else {
+                    break;
+                };
+                let found = captures.get(0).map_or(start..start, |found| found.range());
+                matched = true;
+                let replacement = substitution.expand(&captures);
+                let answer = if confirm {
+                    let (x, y) = buffers.current().grapheme_position((found.start, row));
+                    terminal.goto(buffers.current(), x, y)?;
+                    self.message = format!("replace with {replacement} (y/n/a/q/l)?");
+                    self.render(buffers, windows, terminal)?;
+                    Self::read_answer(terminal)?
+                } else {
+                    Answer::Yes
+                };
+                if matches!(answer, Answer::Quit) {
+                    break 'rows;
+                }
+                let mut end = found.end;
+                if !matches!(answer, Answer::No) {
+                    buffers
+                        .current_mut()
+                        .replace_text(row, found.clone(), &replacement)?;
+                    end = found.start + replacement.len();
+                    count += 1;
+                    replaced_on_line = true;
+                    last_line = Some(row);
+                }
+                if matches!(answer, Answer::All) {
+                    confirm = false;
+                }
+                if matches!(answer, Answer::Last) {
+                    lines += 1;
+                    break 'rows;
+                }
+                if found.is_empty() {
+                    let line = buffers.current().get_line(row).unwrap_or_default();
+                    match line[end..].chars().next() {
+                        Some(c) => end += c.len_utf8(),
+                        None => break,
+                    }
+                }
+                if !substitution.global {
+                    break;
+                }
+                start = end;
+            }
+            if replaced_on_line {
+                lines += 1;
+            }
+        }
+        buffers.current_mut().end_undo_group();
+
+        let Some(row) = last_line else {
+            self.message = if matched {
+                String::new()
+            } else {
+                format!("Pattern not found: {}", regex.as_str())
+            };
+            return Ok(());
+        };
+        let plural = |n: usize| if n == 1 { "" } else { "s" };
+        self.message = format!(
+            "{count} substitution{} on {lines} line{}",
+            plural(count),
+            plural(lines)
+        );
+        let indent = buffers
+            .current()
+            .get_line(row)
+            .unwrap_or_default()
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .count();
+        terminal.goto(buffers.current(), indent, row)
+    }
+
+    fn substitution_target(
+        &mut self,
+        buffer: &Buffer,
+        current: usize,
+        substitution: &Substitution,
+    ) -> Result<(Regex, usize, usize), String> {
+        let pattern = if substitution.pattern.is_empty() {
+            self.search_pattern
+                .clone()
+                .ok_or_else(|| String::from("No previous regular expression"))?
+        } else {
+            substitution.pattern.clone()
+        };
+        let regex = Self::compile(&pattern, substitution.ignore_case)?;
+        self.search_pattern = Some(pattern);
+        let (first, last) = match substitution.range {
+            Some(range) => range.resolve(current, buffer.len())?,
+            None => (current, current),
+        };
+        Ok((regex, first, last))
+    }
+
+    fn read_answer(terminal: &mut Terminal) -> Result<Answer, std::io::Error> {
+        loop {
+            let Some(event) = terminal.read_event()? else {
+                return Ok(Answer::Quit);
+            };
+            let Event::Key(event) = event else {
+                continue;
+            };
+            match event.code {
+                KeyCode::Char('y') => return Ok(Answer::Yes),
+                KeyCode::Char('n') => return Ok(Answer::No),
+                KeyCode::Char('a') => return Ok(Answer::All),
+                KeyCode::Char('l') => return Ok(Answer::Last),
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(Answer::Quit),
+                _ => (),
+            }
+        }
+    }
+
+    fn previous_command_boundary(&self) -> usize {
+        self.command_text[..self.command_cursor]
+            .graphemes(true)
+            .next_back()
+            .map_or(0, |grapheme| self.command_cursor - grapheme.len())
+    }
+
+    fn next_command_boundary(&self) -> usize {
+        self.command_text[self.command_cursor..]
+            .graphemes(true)
+            .next()
+            .map_or(self.command_cursor, |grapheme| {
+                self.command_cursor + grapheme.len()
+            })
+    }
+
+    fn enter_insert_mode(&mut self, buffer: &mut Buffer, terminal: &mut Terminal) -> IOResult {
+        buffer.begin_undo_group();
+        self.set_mode(terminal, EditorMode::Insert)
+    }
+
+    /// Keeps the keys typed in the Insert mode just entered, to type them
+    /// again `count - 1` more times when it ends.
+    fn repeat_insert(&mut self, count: Option<usize>) {
+        self.insert_repeat = count
+            .filter(|count| *count > 1)
+            .map(|count| (count - 1, Vec::new()));
+    }
+
+    fn undo(&mut self, buffer: &mut Buffer, terminal: &mut Terminal) -> IOResult {
+        match buffer.undo() {
+            Some((x, y)) => Self::restore_cursor(buffer, terminal, x, y)?,
+            None => self.message = String::from("Already at oldest change"),
+        }
+        Ok(())
+    }
+
+    fn redo(&mut self, buffer: &mut Buffer, terminal: &mut Terminal) -> IOResult {
+        match buffer.redo() {
+            Some((x, y)) => Self::restore_cursor(buffer, terminal, x, y)?,
+            None => self.message = String::from("Already at newest change"),
+        }
+        Ok(())
+    }
+
+    fn restore_cursor(buffer: &Buffer, terminal: &mut Terminal, x: usize, y: usize) -> IOResult {
+        let y = usize::min(y, buffer.len() - 1);
+        let line_len = buffer.line_len(y);
+        let x = usize::min(x, usize::max(line_len, 1) - 1);
+        terminal.goto(buffer, x, y)
+    }
+
+    fn execute_command(
+        &mut self,
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
+        command: Command,
+    ) -> IOResult {
+        let buffer = buffers.current_mut();
+        match command {
+            Command::Substitute(substitution) => {
+                self.substitute(buffers, windows, terminal, &substitution)?;
+            }
+            Command::Write(file_path) => {
+                self.write_buffer(buffer, file_path.as_deref());
+            }
+            Command::Quit { force } => {
+                self.quit_window(buffers, windows, terminal, force)?;
+            }
+            Command::QuitAll { force } => {
+                self.should_quit = force || !self.has_unsaved_changes(buffers);
+            }
+            Command::WriteQuit(file_path) => {
+                if self.write_buffer(buffer, file_path.as_deref()) {
+                    self.quit_window(buffers, windows, terminal, false)?;
+                }
+            }
+            Command::Exit => {
+                if !buffer.is_modified() || self.write_buffer(buffer, None) {
+                    self.quit_window(buffers, windows, terminal, false)?;
+                }
+            }
+            Command::Edit {
+                file_path: Some(file_path),
+                ..
+            } if buffer
+                .file_path()
+                .map_or(buffer.is_modified(), |path| path != file_path) =>
+            {
+                self.open_buffer(buffers, windows, terminal, &file_path)?;
+            }
+            Command::Edit { file_path, force } => {
+                if buffer.is_modified() && !force {
+                    self.message = String::from("No write since last change (add ! to override)");
+                    return Ok(());
+                }
+                let file_path = file_path.or_else(|| buffer.file_path().map(String::from));
+                if file_path.is_none() {
+                    self.message = String::from("No file name");
+                    return Ok(());
+                }
+                if let Err(err) = buffer.read_file(file_path.as_deref()) {
+                    self.message = err.to_string();
+                    return Ok(());
+                }
+                terminal.goto(buffer, 0, 0)?;
+                self.message = format!("\"{}\" {}L", file_path.unwrap_or_default(), buffer.len());
+            }
+            Command::SaveAs(file_path) => {
+                let previous = buffer.file_path().map(String::from);
+                buffer.set_file_path(Some(&file_path));
+                if !self.write_buffer(buffer, None) {
+                    buffer.set_file_path(previous.as_deref());
+                }
+            }
+            Command::NextBuffer => {
+                let index = (buffers.current_index() + 1) % buffers.len();
+                self.switch_buffer(buffers, windows, terminal, index)?;
+            }
+            Command::PreviousBuffer => {
+                let index = (buffers.current_index() + buffers.len() - 1) % buffers.len();
+                self.switch_buffer(buffers, windows, terminal, index)?;
+            }
+            Command::Buffer(number) => match buffers.index_of_number(number) {
+                Some(index) => self.switch_buffer(buffers, windows, terminal, index)?,
+                None => self.message = format!("Buffer {number} does not exist"),
+            },
+            Command::ListBuffers => {
+                buffers.save_view(terminal.virtual_cursor(), terminal.virtual_position());
+                self.message = Self::list_buffers(buffers);
+            }
+            Command::DeleteBuffer { number, force } => {
+                self.delete_buffer(buffers, windows, terminal, number, force)?;
+            }
+            Command::Split(file_path) => {
+                self.split_window(
+                    buffers,
+                    windows,
+                    terminal,
+                    Orientation::Horizontal,
+                    file_path,
+                )?;
+            }
+            Command::VerticalSplit(file_path) => {
+                self.split_window(buffers, windows, terminal, Orientation::Vertical, file_path)?;
+            }
+            Command::Close => self.close_window(buffers, windows, terminal)?,
+            Command::Set(options) => self.set_options(terminal, &options)?,
+            Command::Map { .. } | Command::Unmap { .. } => self.map_command(terminal, command),
+        }
+        Ok(())
+    }
+
+    fn has_unsaved_changes(&mut self, buffers: &BufferList) -> bool {
+        if buffers.current().is_modified() {
+            self.message = String::from("No write since last change (add ! to override)");
+            return true;
+        }
+        let Some(index) = buffers.modified_index() else {
+            return false;
+        };
+        self.message = format!(
+            "No write since last change for buffer \"{}\"",
+            buffers.get(index).name()
+        );
+        true
+    }
+
+    fn open_buffer(
+        &mut self,
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
+        file_path: &str,
+    ) -> IOResult {
+        let index = match buffers.index_of_path(file_path) {
+            Some(index) => index,
+            None => match buffers.open(file_path) {
+                Ok(index) => index,
+                Err(err) => {
+                    self.message = err.to_string();
+                    return Ok(());
+                }
+            },
+        };
+        self.switch_buffer(buffers, windows, terminal, index)
+    }
+
+    fn switch_buffer(
+        &mut self,
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
+        index: usize,
+    ) -> IOResult {
+        self.move_jumps(buffers);
+        buffers.save_view(terminal.virtual_cursor(), terminal.virtual_position());
+        buffers.select(index);
+        windows.set_buffer(buffers.number(index));
+        Self::restore_view(buffers, terminal)?;
+        let buffer = buffers.current();
+        self.message = format!("\"{}\" {}L", buffer.name(), buffer.len());
+        Ok(())
+    }
+
+    fn restore_view(buffers: &BufferList, terminal: &mut Terminal) -> IOResult {
+        let ((x, y), position) = buffers.view(buffers.current_index());
+        terminal.set_virtual_position(position);
+        Self::restore_cursor(buffers.current(), terminal, x, y)
+    }
+
+    fn list_buffers(buffers: &BufferList) -> String {
+        (0..buffers.len())
+            .map(|index| {
+                let buffer = buffers.get(index);
+                let ((_x, y), _position) = buffers.view(index);
+                format!(
+                    "{:>3} {}{} \"{}\" line {}",
+                    buffers.number(index),
+                    if index == buffers.current_index() {
+                        '%'
+                    } else {
+                        ' '
+                    },
+                    if buffer.is_modified() { '+' } else { ' ' },
+                    buffer.name(),
+                    y + 1
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn delete_buffer(
+        &mut self,
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
+        number: Option<usize>,
+        force: bool,
+    ) -> IOResult {
+        let index = match number {
+            Some(number) => buffers.index_of_number(number),
+            None => Some(buffers.current_index()),
+        };
+        let Some(index) = index else {
+            self.message = String::from("No buffers were deleted");
+            return Ok(());
+        };
+        if buffers.get(index).is_modified() && !force {
+            self.message = format!(
+                "No write since last change for buffer {} (add ! to override)",
+                buffers.number(index)
+            );
+            return Ok(());
+        }
+        let current = buffers.current_index();
+        buffers.save_view(terminal.virtual_cursor(), terminal.virtual_position());
+        let number = buffers.number(index);
+        buffers.remove(index);
+        windows.replace_buffer(number, buffers.number(buffers.current_index()));
+        if index == current {
+            Self::restore_view(buffers, terminal)?;
+        }
+        Ok(())
+    }
+
+    /// Runs `:map`, which maps keys or lists the mappings starting with
+    /// them, and `:unmap`.
+    fn map_command(&mut self, terminal: &Terminal, command: Command) {
+        let leader = &terminal.options().leader;
+        match command {
+            Command::Map {
+                modes,
+                keys: Some(keys),
+                action: Some(action),
+            } => self.keymap.map(&modes, &keys, &action, leader),
+            Command::Map { modes, keys, .. } => {
+                self.message =
+                    self.keymap
+                        .list(&modes, keys.as_deref().unwrap_or_default(), leader);
+                if self.message.is_empty() {
+                    self.message = String::from("No mapping found");
+                }
+            }
+            Command::Unmap { modes, keys } if !self.keymap.unmap(&modes, &keys, leader) => {
+                self.message = String::from("No such mapping");
+            }
+            _ => (),
+        }
+    }
+
+    /// Applies the arguments of `:set`, showing the values asked for or the
+    /// first error.
+    fn set_options(&mut self, terminal: &mut Terminal, options: &[String]) -> IOResult {
+        let mut shown = Vec::new();
+        for option in options {
+            match terminal.apply_option(option) {
+                Ok(Some(value)) => shown.push(value),
+                Ok(None) => (),
+                Err(message) => {
+                    shown = vec![message];
+                    break;
+                }
+            }
+        }
+        self.message = shown.join(" ");
+        self.set_mode(terminal, self.mode)
+    }
+
+    fn window_command(
+        &mut self,
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
+        code: KeyCode,
+    ) -> IOResult {
+        let direction = match code {
+            KeyCode::Char('h') | KeyCode::Left => Direction::Left,
+            KeyCode::Char('j') | KeyCode::Down => Direction::Down,
+            KeyCode::Char('k') | KeyCode::Up => Direction::Up,
+            KeyCode::Char('l') | KeyCode::Right => Direction::Right,
+            KeyCode::Char('w') => {
+                let id = windows.next();
+                return Self::switch_window(buffers, windows, terminal, id);
+            }
+            KeyCode::Char('s') => {
+                return self.split_window(buffers, windows, terminal, Orientation::Horizontal, None)
+            }
+            KeyCode::Char('v') => {
+                return self.split_window(buffers, windows, terminal, Orientation::Vertical, None)
+            }
+            KeyCode::Char('c') => return self.close_window(buffers, windows, terminal),
+            KeyCode::Char('q') => return self.quit_window(buffers, windows, terminal, false),
+            _ => return Ok(()),
+        };
+        let area = screen::text_area(terminal)?;
+        let rect = windows.active_rect(area);
+        let (x, y) = terminal.screen_cursor(buffers.current())?;
+        match windows.neighbour(area, direction, (rect.x + x, rect.y + y)) {
+            Some(id) => Self::switch_window(buffers, windows, terminal, id),
+            None => Ok(()),
+        }
+    }
+
+    fn switch_window(
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
+        id: usize,
+    ) -> IOResult {
+        windows.save_view(terminal.virtual_cursor(), terminal.virtual_position());
+        Self::focus_window(buffers, windows, terminal, id)
+    }
+
+    fn focus_window(
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
+        id: usize,
+    ) -> IOResult {
+        windows.select(id);
+        if let Some(index) = buffers.index_of_number(windows.buffer(id)) {
+            buffers.select(index);
+        }
+        let ((x, y), position) = windows.view(id);
+        let rect = windows.active_rect(screen::text_area(terminal)?);
+        terminal.set_view_size((rect.width, rect.height));
+        terminal.set_virtual_position(position);
+        Self::restore_cursor(buffers.current(), terminal, x, y)
+    }
+
+    fn split_window(
+        &mut self,
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
+        orientation: Orientation,
+        file_path: Option<String>,
+    ) -> IOResult {
+        windows.save_view(terminal.virtual_cursor(), terminal.virtual_position());
+        let id = windows.split(orientation);
+        Self::focus_window(buffers, windows, terminal, id)?;
+        match file_path {
+            Some(file_path) => self.open_buffer(buffers, windows, terminal, &file_path),
+            None => Ok(()),
+        }
+    }
+
+    fn close_window(
+        &mut self,
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
+    ) -> IOResult {
+        let Some(id) = windows.close() else {
+            self.message = String::from("Cannot close last window");
+            return Ok(());
+        };
+        Self::focus_window(buffers, windows, terminal, id)
+    }
+
+    fn quit_window(
+        &mut self,
+        buffers: &mut BufferList,
+        windows: &mut WindowList,
+        terminal: &mut Terminal,
+        force: bool,
+    ) -> IOResult {
+        if windows.len() > 1 {
+            return self.close_window(buffers, windows, terminal);
+        }
+        self.should_quit = force || !self.has_unsaved_changes(buffers);
         Ok(())
     }
+
+    fn write_buffer(&mut self, buffer: &mut Buffer, file_path: Option<&str>) -> bool {
+        match buffer.write_file(file_path) {
+            Ok(()) => {
+                let name = file_path.or(buffer.file_path()).unwrap_or_default();
+                self.message = format!("\"{name}\" {}L written", buffer.len());
+                true
+            }
+            Err(err) => {
+                self.message = err.to_string();
+                false
+            }
+        }
+    }
+}
+
+fn multiply_counts(first: Option<usize>, second: Option<usize>) -> Option<usize> {
+    match (first, second) {
+        (None, None) => None,
+        _ => Some(first.unwrap_or(1).saturating_mul(second.unwrap_or(1))),
+    }
 }
@@ -0,0 +1,91 @@
+use std::borrow::Cow;
+
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+pub fn width(text: &str) -> usize {
+    text.width()
+}
+
+/// How many cells a tab at `cell` takes up to reach the next tab stop.
+pub fn tab_width(cell: usize, tab_stop: usize) -> usize {
+    let tab_stop = usize::max(tab_stop, 1);
+    tab_stop - cell % tab_stop
+}
+
+/// The graphemes of `line` with their byte indices, with each tab replaced
+/// by the spaces up to the next tab stop.
+pub fn expand_tabs(line: &str, tab_stop: usize) -> impl Iterator<Item = (usize, Cow<'_, str>)> {
+    let mut cell = 0;
+    line.grapheme_indices(true).map(move |(index, grapheme)| {
+        let grapheme = if grapheme == "\t" {
+            Cow::Owned(" ".repeat(tab_width(cell, tab_stop)))
+        } else {
+            Cow::Borrowed(grapheme)
+        };
+        cell += width(&grapheme);
+        (index, grapheme)
+    })
+}
+
+pub fn width_until(line: &str, column: usize, tab_stop: usize) -> usize {
+    expand_tabs(line, tab_stop)
+        .take(column)
+        .map(|(_index, grapheme)| width(&grapheme))
+        .sum()
+}
+
+pub fn width_at(line: &str, column: usize, tab_stop: usize) -> usize {
+    expand_tabs(line, tab_stop)
+        .nth(column)
+        .map_or(1, |(_index, grapheme)| usize::max(width(&grapheme), 1))
+}
+
+pub fn clip(
+    line: &str,
+    start: usize,
+    columns: usize,
+    tab_stop: usize,
+) -> Vec<(usize, Cow<'_, str>)> {
+    let mut pieces = Vec::new();
+    let mut cell = 0;
+    for (index, grapheme) in expand_tabs(line, tab_stop) {
+        let end = cell + width(&grapheme);
+        if end > start + columns {
+            break;
+        }
+        if cell >= start {
+            pieces.push((index, grapheme));
+        } else if end > start {
+            pieces.push((index, Cow::Owned(" ".repeat(end - start))));
+        }
+        cell = end;
+    }
+    pieces
+}
+
+/// The grapheme columns at which each screen row of `line` starts when it is
+/// wrapped to `columns` cells. With `at_words`, rows break after the last
+/// whitespace that fits rather than in the middle of a word.
+pub fn wrap(line: &str, columns: usize, at_words: bool, tab_stop: usize) -> Vec<usize> {
+    let mut starts = vec![0];
+    let mut cell = 0;
+    let mut break_after = None;
+    for (column, (_index, grapheme)) in expand_tabs(line, tab_stop).enumerate() {
+        let grapheme_width = width(&grapheme);
+        while cell + grapheme_width > columns && column > starts[starts.len() - 1] {
+            if let Some((break_column, break_cell)) = break_after.take() {
+                starts.push(break_column);
+                cell -= break_cell;
+            } else {
+                starts.push(column);
+                cell = 0;
+            }
+        }
+        cell += grapheme_width;
+        if at_words && grapheme.chars().all(char::is_whitespace) {
+            break_after = Some((column + 1, cell));
+        }
+    }
+    starts
+}
@@ -0,0 +1,143 @@
+use crossterm::style::{Attributes, Color};
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::display;
+
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Style {
+    pub foreground: Option<Color>,
+    pub background: Option<Color>,
+    pub attributes: Attributes,
+}
+
+#[derive(Clone, PartialEq)]
+struct Cell {
+    symbol: String,
+    style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {
+            symbol: String::from(" "),
+            style: Style::default(),
+        }
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Frame {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+    cursor: (usize, usize),
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize) -> Self {
+        Frame {
+            width,
+            height,
+            cells: vec![Cell::default(); width * height],
+            cursor: (0, 0),
+        }
+    }
+
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        self.cursor
+    }
+
+    pub fn set_cursor(&mut self, x: usize, y: usize) {
+        self.cursor = (
+            usize::min(x, self.width.saturating_sub(1)),
+            usize::min(y, self.height.saturating_sub(1)),
+        );
+    }
+
+    pub fn clear_row(&mut self, y: usize, from: usize) {
+        if y >= self.height {
+            return;
+        }
+        for cell in
+            &mut self.cells[y * self.width + usize::min(from, self.width)..(y + 1) * self.width]
+        {
+            *cell = Cell::default();
+        }
+    }
+
+    pub fn print(&mut self, x: usize, y: usize, text: &str, style: Style) -> usize {
+        if y >= self.height {
+            return x;
+        }
+        let mut x = x;
+        for grapheme in text.graphemes(true) {
+            let grapheme = if grapheme == "\t" { " " } else { grapheme };
+            let width = display::width(grapheme);
+            if width == 0 {
+                if x > 0 {
+                    self.cells[y * self.width + x - 1].symbol.push_str(grapheme);
+                }
+                continue;
+            }
+            if x + width > self.width {
+                break;
+            }
+            let index = y * self.width + x;
+            self.cells[index] = Cell {
+                symbol: String::from(grapheme),
+                style,
+            };
+            for cell in &mut self.cells[index + 1..index + width] {
+                cell.symbol.clear();
+                cell.style = style;
+            }
+            x += width;
+        }
+        x
+    }
+
+    fn row(&self, y: usize) -> &[Cell] {
+        &self.cells[y * self.width..(y + 1) * self.width]
+    }
+
+    pub fn row_text(&self, y: usize) -> String {
+        self.row(y)
+            .iter()
+            .map(|cell| cell.symbol.as_str())
+            .collect()
+    }
+
+    pub fn style_at(&self, x: usize, y: usize) -> Style {
+        self.cells[y * self.width + x].style
+    }
+
+    pub fn diff(&self, previous: &Frame) -> Vec<(usize, usize, Style, String)> {
+        let mut changes = Vec::new();
+        for y in 0..self.height {
+            let row = self.row(y);
+            let previous_row = previous.row(y);
+            let mut x = 0;
+            while x < self.width {
+                if row[x] == previous_row[x] {
+                    x += 1;
+                    continue;
+                }
+                let mut start = x;
+                while start > 0 && row[start].symbol.is_empty() {
+                    start -= 1;
+                }
+                let style = row[start].style;
+                while x < self.width && row[x] != previous_row[x] && row[x].style == style {
+                    x += 1;
+                }
+                let text = row[start..x].iter().map(|cell| cell.symbol.as_str());
+                changes.push((start, y, style, text.collect()));
+            }
+        }
+        changes
+    }
+}
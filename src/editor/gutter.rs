@@ -0,0 +1,42 @@
+/// How the line-number gutter labels rows. `Hybrid` shows the absolute
+/// number on the cursor row and relative numbers everywhere else.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum LineNumbers {
+    #[default]
+    Off,
+    Absolute,
+    Relative,
+    Hybrid,
+}
+
+impl LineNumbers {
+    pub fn from_flags(number: bool, relative: bool) -> Self {
+        match (number, relative) {
+            (false, false) => LineNumbers::Off,
+            (true, false) => LineNumbers::Absolute,
+            (false, true) => LineNumbers::Relative,
+            (true, true) => LineNumbers::Hybrid,
+        }
+    }
+}
+
+const MIN_DIGITS: usize = 3;
+
+/// The width of the gutter including the space that separates it from the
+/// text, sized so the largest line number of the buffer fits.
+pub fn width(line_numbers: LineNumbers, line_count: usize) -> usize {
+    if line_numbers == LineNumbers::Off {
+        return 0;
+    }
+    usize::max(line_count.to_string().len(), MIN_DIGITS) + 1
+}
+
+pub fn label(line_numbers: LineNumbers, width: usize, row: usize, cursor_row: usize) -> String {
+    let number = match line_numbers {
+        LineNumbers::Off => return String::new(),
+        LineNumbers::Absolute => row + 1,
+        LineNumbers::Hybrid if row == cursor_row => row + 1,
+        LineNumbers::Relative | LineNumbers::Hybrid => row.abs_diff(cursor_row),
+    };
+    format!("{number:>0$} ", width - 1)
+}
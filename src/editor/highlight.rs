@@ -0,0 +1,239 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::ffi::OsStr;
+use std::path::Path;
+
+use crossterm::style::{Attribute, Color};
+
+use super::frame::Style;
+
+mod json;
+mod markdown;
+mod rust;
+mod toml;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Highlight {
+    Comment,
+    Keyword,
+    Type,
+    Function,
+    Macro,
+    String,
+    Number,
+    Constant,
+    Attribute,
+    Lifetime,
+    Key,
+    Heading,
+    Emphasis,
+    Strong,
+    Code,
+    Link,
+    Punctuation,
+}
+
+impl Highlight {
+    fn style(self) -> Style {
+        let foreground = |color| Style {
+            foreground: Some(color),
+            ..Style::default()
+        };
+        match self {
+            Highlight::Comment => foreground(Color::DarkGrey),
+            Highlight::Keyword => foreground(Color::Magenta),
+            Highlight::Type => foreground(Color::Yellow),
+            Highlight::Function | Highlight::Key => foreground(Color::Blue),
+            Highlight::Macro => foreground(Color::Cyan),
+            Highlight::String | Highlight::Code => foreground(Color::Green),
+            Highlight::Number | Highlight::Constant => foreground(Color::DarkYellow),
+            Highlight::Attribute => foreground(Color::DarkCyan),
+            Highlight::Lifetime => foreground(Color::DarkMagenta),
+            Highlight::Punctuation => foreground(Color::Red),
+            Highlight::Heading => Style {
+                attributes: Attribute::Bold.into(),
+                ..foreground(Color::Blue)
+            },
+            Highlight::Emphasis => Style {
+                attributes: Attribute::Italic.into(),
+                ..Style::default()
+            },
+            Highlight::Strong => Style {
+                attributes: Attribute::Bold.into(),
+                ..Style::default()
+            },
+            Highlight::Link => Style {
+                attributes: Attribute::Underlined.into(),
+                ..foreground(Color::Cyan)
+            },
+        }
+    }
+}
+
+/// The colors highlighted text is drawn in. `Plain` keeps only the bold,
+/// italic and underlined text of the default theme.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Theme {
+    Default,
+    Plain,
+}
+
+impl Theme {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Theme::Default),
+            "plain" => Some(Theme::Plain),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Default => "default",
+            Theme::Plain => "plain",
+        }
+    }
+
+    pub fn style(self, highlight: Highlight) -> Style {
+        match self {
+            Theme::Default => highlight.style(),
+            Theme::Plain => Style {
+                foreground: None,
+                ..highlight.style()
+            },
+        }
+    }
+}
+
+/// A highlighted byte range of a single line.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub highlight: Highlight,
+}
+
+/// The construct a line starts inside of, for constructs that can span
+/// several lines.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub enum State {
+    #[default]
+    Normal,
+    BlockComment(usize),
+    String,
+    RawString(usize),
+    MultilineString(u8),
+    CodeBlock(u8),
+}
+
+pub trait Grammar {
+    /// Appends the spans of `line` and returns the state the next line
+    /// starts in.
+    fn highlight_line(&self, line: &str, state: State, spans: &mut Vec<Span>) -> State;
+}
+
+/// Highlights the lines of one buffer, remembering the state each line
+/// starts in so only lines after an edit need to be tokenized again.
+pub struct Highlighter {
+    grammar: Option<&'static dyn Grammar>,
+    states: RefCell<Vec<State>>,
+}
+
+impl Default for Highlighter {
+    fn default() -> Self {
+        Highlighter {
+            grammar: None,
+            states: RefCell::new(vec![State::Normal]),
+        }
+    }
+}
+
+impl Highlighter {
+    pub fn for_path(file_path: Option<&str>) -> Self {
+        let extension = file_path
+            .and_then(|path| Path::new(path).extension())
+            .and_then(OsStr::to_str);
+        let grammar: Option<&'static dyn Grammar> = match extension {
+            Some("rs") => Some(&rust::Rust),
+            Some("toml") => Some(&toml::Toml),
+            Some("json") => Some(&json::Json),
+            Some("md" | "markdown") => Some(&markdown::Markdown),
+            _ => None,
+        };
+        Highlighter {
+            grammar,
+            ..Highlighter::default()
+        }
+    }
+
+    pub fn invalidate(&mut self, row: usize) {
+        self.states.get_mut().truncate(row + 1);
+    }
+
+    pub fn highlight_line<'a>(
+        &self,
+        row: usize,
+        get_line: impl Fn(usize) -> Cow<'a, str>,
+    ) -> Vec<Span> {
+        let Some(grammar) = self.grammar else {
+            return Vec::new();
+        };
+        let mut states = self.states.borrow_mut();
+        let mut spans = Vec::new();
+        while states.len() <= row {
+            let index = states.len() - 1;
+            let state = grammar.highlight_line(&get_line(index), states[index], &mut spans);
+            states.push(state);
+            spans.clear();
+        }
+        grammar.highlight_line(&get_line(row), states[row], &mut spans);
+        spans
+    }
+}
+
+fn push(spans: &mut Vec<Span>, start: usize, end: usize, highlight: Highlight) {
+    if end > start {
+        spans.push(Span {
+            start,
+            end,
+            highlight,
+        });
+    }
+}
+
+fn scan_while(bytes: &[u8], start: usize, predicate: impl Fn(u8) -> bool) -> usize {
+    start
+        + bytes
+            .get(start..)
+            .map_or(0, |rest| rest.iter().take_while(|&&b| predicate(b)).count())
+}
+
+fn is_identifier(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte >= 0x80
+}
+
+fn is_whitespace(byte: u8) -> bool {
+    byte == b' ' || byte == b'\t'
+}
+
+/// Scans a string body that starts at `start`, returning the index just past
+/// the closing `quote`, or `None` if the line ends first.
+fn scan_quoted(bytes: &[u8], start: usize, quote: u8, escapes: bool) -> Option<usize> {
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' if escapes => i += 2,
+            byte if byte == quote => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+fn find(bytes: &[u8], start: usize, pattern: &[u8]) -> Option<usize> {
+    bytes
+        .get(start..)?
+        .windows(pattern.len())
+        .position(|window| window == pattern)
+        .map(|position| position + start)
+}
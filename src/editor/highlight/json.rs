@@ -0,0 +1,39 @@
+use super::{is_whitespace, push, scan_quoted, scan_while, Grammar, Highlight, Span, State};
+
+pub struct Json;
+
+impl Grammar for Json {
+    fn highlight_line(&self, line: &str, _state: State, spans: &mut Vec<Span>) -> State {
+        let bytes = line.as_bytes();
+        let mut i = 0;
+        while i < bytes.len() {
+            let byte = bytes[i];
+            if byte == b'"' {
+                let end = scan_quoted(bytes, i + 1, b'"', true).unwrap_or(bytes.len());
+                let after = scan_while(bytes, end, is_whitespace);
+                let highlight = if bytes.get(after) == Some(&b':') {
+                    Highlight::Key
+                } else {
+                    Highlight::String
+                };
+                push(spans, i, end, highlight);
+                i = end;
+            } else if byte == b'-' || byte.is_ascii_digit() {
+                let end = scan_while(bytes, i + 1, |b| {
+                    b.is_ascii_digit() || b"eE.+-".contains(&b)
+                });
+                push(spans, i, end, Highlight::Number);
+                i = end;
+            } else if byte.is_ascii_alphabetic() {
+                let end = scan_while(bytes, i, |b| b.is_ascii_alphanumeric());
+                if ["true", "false", "null"].contains(&&line[i..end]) {
+                    push(spans, i, end, Highlight::Constant);
+                }
+                i = end;
+            } else {
+                i += 1;
+            }
+        }
+        State::Normal
+    }
+}
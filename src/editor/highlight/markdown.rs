@@ -0,0 +1,110 @@
+use super::{find, is_whitespace, push, scan_while, Grammar, Highlight, Span, State};
+
+pub struct Markdown;
+
+impl Grammar for Markdown {
+    fn highlight_line(&self, line: &str, state: State, spans: &mut Vec<Span>) -> State {
+        let bytes = line.as_bytes();
+        let indent = scan_while(bytes, 0, is_whitespace);
+        let fence = [b'`', b'~']
+            .into_iter()
+            .find(|&fence| indent < 4 && bytes[indent..].starts_with(&[fence; 3]));
+        if let State::CodeBlock(open) = state {
+            push(spans, 0, bytes.len(), Highlight::Code);
+            return if fence == Some(open) && scan_while(bytes, indent, |b| b == open) == bytes.len()
+            {
+                State::Normal
+            } else {
+                state
+            };
+        }
+        if let Some(fence) = fence {
+            push(spans, 0, bytes.len(), Highlight::Code);
+            return State::CodeBlock(fence);
+        }
+        if indent < 4 {
+            let hashes = scan_while(bytes, indent, |b| b == b'#') - indent;
+            if (1..=6).contains(&hashes)
+                && bytes.get(indent + hashes).is_none_or(|&b| is_whitespace(b))
+            {
+                push(spans, indent, bytes.len(), Highlight::Heading);
+                return State::Normal;
+            }
+            if is_thematic_break(&bytes[indent..]) {
+                push(spans, indent, bytes.len(), Highlight::Punctuation);
+                return State::Normal;
+            }
+        }
+        let mut i = indent;
+        while bytes.get(i) == Some(&b'>') {
+            push(spans, i, i + 1, Highlight::Punctuation);
+            i = scan_while(bytes, i + 1, is_whitespace);
+        }
+        let marker_end = match bytes.get(i) {
+            Some(b'-' | b'*' | b'+') => Some(i + 1),
+            Some(b) if b.is_ascii_digit() => {
+                let end = scan_while(bytes, i, |b| b.is_ascii_digit());
+                matches!(bytes.get(end), Some(b'.' | b')')).then_some(end + 1)
+            }
+            _ => None,
+        };
+        if let Some(end) =
+            marker_end.filter(|&end| bytes.get(end).is_none_or(|&b| is_whitespace(b)))
+        {
+            push(spans, i, end, Highlight::Punctuation);
+            i = end;
+        }
+        inline(bytes, i, spans);
+        State::Normal
+    }
+}
+
+fn is_thematic_break(bytes: &[u8]) -> bool {
+    let Some(&first) = bytes.first() else {
+        return false;
+    };
+    b"-*_".contains(&first)
+        && bytes.iter().all(|&b| b == first || is_whitespace(b))
+        && bytes.iter().filter(|&&b| b == first).nth(2).is_some()
+}
+
+fn inline(bytes: &[u8], start: usize, spans: &mut Vec<Span>) {
+    let mut i = start;
+    while i < bytes.len() {
+        let byte = bytes[i];
+        let end = match byte {
+            b'`' => {
+                let ticks = scan_while(bytes, i, |b| b == b'`');
+                find(bytes, ticks, &bytes[i..ticks]).map(|end| (end + ticks - i, Highlight::Code))
+            }
+            b'*' | b'_' if byte == b'*' || i == 0 || !bytes[i - 1].is_ascii_alphanumeric() => {
+                let run = usize::min(scan_while(bytes, i, |b| b == byte) - i, 2);
+                let delimiter = &bytes[i..i + run];
+                let highlight = if run == 2 {
+                    Highlight::Strong
+                } else {
+                    Highlight::Emphasis
+                };
+                bytes
+                    .get(i + run)
+                    .filter(|&&b| !is_whitespace(b))
+                    .and_then(|_| find(bytes, i + run + 1, delimiter))
+                    .map(|end| (end + run, highlight))
+            }
+            b'[' => find(bytes, i, b"](")
+                .and_then(|middle| find(bytes, middle, b")"))
+                .map(|end| (end + 1, Highlight::Link)),
+            b'<' if bytes[i..].starts_with(b"<http") => {
+                find(bytes, i, b">").map(|end| (end + 1, Highlight::Link))
+            }
+            _ => None,
+        };
+        match end {
+            Some((end, highlight)) => {
+                push(spans, i, end, highlight);
+                i = end;
+            }
+            None => i += 1,
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use super::{find, is_identifier, push, scan_quoted, scan_while, Grammar, Highlight, Span, State};
+
+pub struct Rust;
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref",
+    "return", "self", "Self", "static", "struct", "super", "trait", "type", "unsafe", "use",
+    "where", "while", "yield",
+];
+
+const PRIMITIVES: &[&str] = &[
+    "bool", "char", "str", "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64",
+    "i128", "isize", "f32", "f64",
+];
+
+impl Grammar for Rust {
+    fn highlight_line(&self, line: &str, state: State, spans: &mut Vec<Span>) -> State {
+        let bytes = line.as_bytes();
+        let mut i = 0;
+        let mut state = state;
+        while i < bytes.len() || state != State::Normal {
+            if state != State::Normal {
+                let start = i;
+                let highlight = match state {
+                    State::BlockComment(_) => Highlight::Comment,
+                    _ => Highlight::String,
+                };
+                (i, state) = finish(bytes, i, state);
+                push(spans, start, i, highlight);
+                if state != State::Normal {
+                    return state;
+                }
+                continue;
+            }
+            let byte = bytes[i];
+            let next = bytes.get(i + 1).copied();
+            if byte == b'/' && next == Some(b'/') {
+                push(spans, i, bytes.len(), Highlight::Comment);
+                break;
+            } else if byte == b'/' && next == Some(b'*') {
+                let start = i;
+                (i, state) = finish(bytes, i + 2, State::BlockComment(1));
+                push(spans, start, i, Highlight::Comment);
+            } else if let Some((body, opened)) = string_start(bytes, i) {
+                let start = i;
+                (i, state) = finish(bytes, body, opened);
+                push(spans, start, i, Highlight::String);
+            } else if byte == b'\'' {
+                i = quote(line, i, spans);
+            } else if byte == b'#' && (next == Some(b'[') || next == Some(b'!')) {
+                let end = find(bytes, i, b"]").map_or(bytes.len(), |end| end + 1);
+                push(spans, i, end, Highlight::Attribute);
+                i = end;
+            } else if byte.is_ascii_digit() {
+                let end = number_end(bytes, i);
+                push(spans, i, end, Highlight::Number);
+                i = end;
+            } else if is_identifier(byte) {
+                i = identifier(line, i, spans);
+            } else {
+                i += 1;
+            }
+        }
+        state
+    }
+}
+
+fn finish(bytes: &[u8], start: usize, state: State) -> (usize, State) {
+    match state {
+        State::BlockComment(depth) => {
+            let mut depth = depth;
+            let mut i = start;
+            while i < bytes.len() {
+                if bytes[i..].starts_with(b"/*") {
+                    depth += 1;
+                    i += 2;
+                } else if bytes[i..].starts_with(b"*/") {
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 {
+                        return (i, State::Normal);
+                    }
+                } else {
+                    i += 1;
+                }
+            }
+            (bytes.len(), State::BlockComment(depth))
+        }
+        State::String => match scan_quoted(bytes, start, b'"', true) {
+            Some(end) => (end, State::Normal),
+            None => (bytes.len(), state),
+        },
+        State::RawString(hashes) => {
+            let mut closing = vec![b'"'];
+            closing.resize(hashes + 1, b'#');
+            match find(bytes, start, &closing) {
+                Some(end) => (end + closing.len(), State::Normal),
+                None => (bytes.len(), state),
+            }
+        }
+        _ => (start, State::Normal),
+    }
+}
+
+fn string_start(bytes: &[u8], start: usize) -> Option<(usize, State)> {
+    let mut i = start;
+    if bytes[i] == b'b' {
+        i += 1;
+    }
+    if bytes.get(i) == Some(&b'r') {
+        let quote = scan_while(bytes, i + 1, |b| b == b'#');
+        return (bytes.get(quote) == Some(&b'"'))
+            .then_some((quote + 1, State::RawString(quote - i - 1)));
+    }
+    (bytes.get(i) == Some(&b'"')).then_some((i + 1, State::String))
+}
+
+fn quote(line: &str, start: usize, spans: &mut Vec<Span>) -> usize {
+    let bytes = line.as_bytes();
+    if bytes.get(start + 1) == Some(&b'\\') {
+        let end = scan_quoted(bytes, start + 1, b'\'', true).unwrap_or(bytes.len());
+        push(spans, start, end, Highlight::String);
+        return end;
+    }
+    let char_len = line[start + 1..].chars().next().map_or(0, char::len_utf8);
+    if char_len > 0 && bytes.get(start + 1 + char_len) == Some(&b'\'') {
+        push(spans, start, start + 2 + char_len, Highlight::String);
+        return start + 2 + char_len;
+    }
+    let end = scan_while(bytes, start + 1, is_identifier);
+    if end == start + 1 {
+        return end;
+    }
+    push(spans, start, end, Highlight::Lifetime);
+    end
+}
+
+fn number_end(bytes: &[u8], start: usize) -> usize {
+    let mut i = start;
+    while i < bytes.len() {
+        let byte = bytes[i];
+        let fraction = byte == b'.' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit);
+        if !(byte.is_ascii_alphanumeric() || byte == b'_' || fraction) {
+            break;
+        }
+        i += 1;
+    }
+    i
+}
+
+fn identifier(line: &str, start: usize, spans: &mut Vec<Span>) -> usize {
+    let bytes = line.as_bytes();
+    let end = scan_while(bytes, start, is_identifier);
+    let word = &line[start..end];
+    let next = bytes.get(end).copied();
+    let (end, highlight) = if KEYWORDS.contains(&word) {
+        (end, Some(Highlight::Keyword))
+    } else if word == "true" || word == "false" {
+        (end, Some(Highlight::Constant))
+    } else if next == Some(b'!') && bytes.get(end + 1) != Some(&b'=') {
+        (end + 1, Some(Highlight::Macro))
+    } else if PRIMITIVES.contains(&word) {
+        (end, Some(Highlight::Type))
+    } else if word.len() > 1
+        && word
+            .bytes()
+            .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit() || b == b'_')
+    {
+        (end, Some(Highlight::Constant))
+    } else if word.starts_with(|c: char| c.is_uppercase()) {
+        (end, Some(Highlight::Type))
+    } else if next == Some(b'(') {
+        (end, Some(Highlight::Function))
+    } else {
+        (end, None)
+    };
+    if let Some(highlight) = highlight {
+        push(spans, start, end, highlight);
+    }
+    end
+}
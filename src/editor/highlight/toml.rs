@@ -0,0 +1,128 @@
+use super::{find, is_whitespace, push, scan_quoted, scan_while, Grammar, Highlight, Span, State};
+
+pub struct Toml;
+
+impl Grammar for Toml {
+    fn highlight_line(&self, line: &str, state: State, spans: &mut Vec<Span>) -> State {
+        let bytes = line.as_bytes();
+        let mut i = 0;
+        if let State::MultilineString(quote) = state {
+            let Some(end) = multiline_end(bytes, 0, quote) else {
+                push(spans, 0, bytes.len(), Highlight::String);
+                return state;
+            };
+            push(spans, 0, end, Highlight::String);
+            i = end;
+        } else {
+            i = scan_while(bytes, i, is_whitespace);
+            if bytes.get(i) == Some(&b'[') {
+                let closing: &[u8] = if bytes.get(i + 1) == Some(&b'[') {
+                    b"]]"
+                } else {
+                    b"]"
+                };
+                let end = find(bytes, i, closing).map_or(bytes.len(), |end| end + closing.len());
+                push(spans, i, end, Highlight::Heading);
+                i = end;
+            }
+        }
+        while i < bytes.len() {
+            let byte = bytes[i];
+            if byte == b'#' {
+                push(spans, i, bytes.len(), Highlight::Comment);
+                break;
+            }
+            if byte == b'"' || byte == b'\'' {
+                if bytes[i..].starts_with(&[byte; 3]) {
+                    let Some(end) = multiline_end(bytes, i + 3, byte) else {
+                        push(spans, i, bytes.len(), Highlight::String);
+                        return State::MultilineString(byte);
+                    };
+                    push(spans, i, end, Highlight::String);
+                    i = end;
+                    continue;
+                }
+                let end = scan_quoted(bytes, i + 1, byte, byte == b'"').unwrap_or(bytes.len());
+                let highlight = if is_key(bytes, end) {
+                    Highlight::Key
+                } else {
+                    Highlight::String
+                };
+                push(spans, i, end, highlight);
+                i = end;
+            } else if is_bare(byte) || byte == b'+' {
+                let end = scan_while(bytes, i + 1, |b| is_bare(b) || b"+:.".contains(&b));
+                let word = &line[i..end];
+                let key_end = scan_while(bytes, i, is_bare);
+                let highlight = if is_key(bytes, key_end) {
+                    Some((key_end, Highlight::Key))
+                } else if word == "true" || word == "false" {
+                    Some((end, Highlight::Constant))
+                } else if word
+                    .trim_start_matches(['+', '-'])
+                    .starts_with(|c: char| c.is_ascii_digit())
+                    || ["inf", "nan"].contains(&word.trim_start_matches(['+', '-']))
+                {
+                    Some((end, Highlight::Number))
+                } else {
+                    None
+                };
+                match highlight {
+                    Some((end, highlight)) => {
+                        push(spans, i, end, highlight);
+                        i = end;
+                    }
+                    None => i = key_end.max(i + 1),
+                }
+            } else {
+                i += 1;
+            }
+        }
+        State::Normal
+    }
+}
+
+fn is_bare(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'-'
+}
+
+/// Whether the key part that ends at `end` is followed by `=`, looking past
+/// any further dotted parts.
+fn is_key(bytes: &[u8], end: usize) -> bool {
+    let mut i = end;
+    loop {
+        i = scan_while(bytes, i, is_whitespace);
+        match bytes.get(i) {
+            Some(b'=') => return true,
+            Some(b'.') => {
+                i = scan_while(bytes, i + 1, is_whitespace);
+                let next = match bytes.get(i) {
+                    Some(&quote @ (b'"' | b'\'')) => {
+                        scan_quoted(bytes, i + 1, quote, quote == b'"')
+                    }
+                    Some(&byte) if is_bare(byte) => Some(scan_while(bytes, i, is_bare)),
+                    _ => None,
+                };
+                let Some(next) = next else {
+                    return false;
+                };
+                i = next;
+            }
+            _ => return false,
+        }
+    }
+}
+
+fn multiline_end(bytes: &[u8], start: usize, quote: u8) -> Option<usize> {
+    let mut i = start;
+    while i < bytes.len() {
+        if quote == b'"' && bytes[i] == b'\\' {
+            i += 2;
+        } else if bytes[i..].starts_with(&[quote; 3]) {
+            return Some(i + 3);
+        } else {
+            i += 1;
+        }
+    }
+    None
+}
@@ -0,0 +1,96 @@
+#[derive(Clone)]
+pub enum Edit {
+    InsertText {
+        row: usize,
+        column: usize,
+        text: String,
+    },
+    DeleteText {
+        row: usize,
+        column: usize,
+        text: String,
+    },
+    InsertLine {
+        row: usize,
+        text: String,
+    },
+    DeleteLine {
+        row: usize,
+        text: String,
+    },
+}
+
+impl Edit {
+    pub fn inverse(&self) -> Edit {
+        match self.clone() {
+            Edit::InsertText { row, column, text } => Edit::DeleteText { row, column, text },
+            Edit::DeleteText { row, column, text } => Edit::InsertText { row, column, text },
+            Edit::InsertLine { row, text } => Edit::DeleteLine { row, text },
+            Edit::DeleteLine { row, text } => Edit::InsertLine { row, text },
+        }
+    }
+
+    pub fn position(&self) -> (usize, usize) {
+        match *self {
+            Edit::InsertText { row, column, .. } | Edit::DeleteText { row, column, .. } => {
+                (column, row)
+            }
+            Edit::InsertLine { row, .. } | Edit::DeleteLine { row, .. } => (0, row),
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct History {
+    undo_stack: Vec<Vec<Edit>>,
+    redo_stack: Vec<Vec<Edit>>,
+    group: Option<Vec<Edit>>,
+    group_depth: usize,
+}
+
+impl History {
+    pub fn record(&mut self, edit: Edit) {
+        self.redo_stack.clear();
+        match &mut self.group {
+            Some(group) => group.push(edit),
+            None => self.undo_stack.push(vec![edit]),
+        }
+    }
+
+    pub fn begin_group(&mut self) {
+        self.group_depth += 1;
+        if self.group.is_none() {
+            self.group = Some(Vec::new());
+        }
+    }
+
+    pub fn end_group(&mut self) {
+        self.group_depth = self.group_depth.saturating_sub(1);
+        if self.group_depth == 0 {
+            self.close_group();
+        }
+    }
+
+    fn close_group(&mut self) {
+        self.group_depth = 0;
+        if let Some(group) = self.group.take() {
+            if !group.is_empty() {
+                self.undo_stack.push(group);
+            }
+        }
+    }
+
+    pub fn undo(&mut self) -> Option<&Vec<Edit>> {
+        self.close_group();
+        let edits = self.undo_stack.pop()?;
+        self.redo_stack.push(edits);
+        self.redo_stack.last()
+    }
+
+    pub fn redo(&mut self) -> Option<&Vec<Edit>> {
+        self.close_group();
+        let edits = self.redo_stack.pop()?;
+        self.undo_stack.push(edits);
+        self.undo_stack.last()
+    }
+}
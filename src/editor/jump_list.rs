@@ -0,0 +1,84 @@
+use super::buffer::LineEdit;
+
+/// The most jumps kept, dropping the oldest beyond it, as in vim.
+const MAX_JUMPS: usize = 100;
+
+/// A place the cursor jumped from: a buffer number and a position in it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Jump {
+    pub buffer: usize,
+    pub position: (usize, usize),
+}
+
+/// The places big jumps were made from, walked back with Ctrl-O and forward
+/// again with Ctrl-I. `origin` is where the cursor was before the key being
+/// handled, which is what a jump made by that key records.
+#[derive(Default)]
+pub struct JumpList {
+    jumps: Vec<Jump>,
+    index: usize,
+    origin: Option<Jump>,
+}
+
+impl JumpList {
+    pub fn set_origin(&mut self, origin: Jump) {
+        self.origin = Some(origin);
+    }
+
+    /// Records that the key being handled jumped away from its origin.
+    pub fn record(&mut self) {
+        if let Some(origin) = self.origin {
+            self.push(origin);
+        }
+    }
+
+    /// Keeps the jumps in `buffer` on their lines when a line is inserted or
+    /// deleted before them. Jumps on a deleted line stay at its row, on the
+    /// line that took its place.
+    pub fn move_rows(&mut self, buffer: usize, edit: LineEdit) {
+        for jump in &mut self.jumps {
+            let row = &mut jump.position.1;
+            match edit {
+                LineEdit::Insert(inserted) if jump.buffer == buffer && *row >= inserted => {
+                    *row += 1;
+                }
+                LineEdit::Delete(deleted) if jump.buffer == buffer && *row > deleted => {
+                    *row -= 1;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Adds `jump` at the end of the list, replacing any older jump from the
+    /// same line and dropping the oldest once there are too many.
+    fn push(&mut self, jump: Jump) {
+        self.jumps
+            .retain(|other| other.buffer != jump.buffer || other.position.1 != jump.position.1);
+        self.jumps.push(jump);
+        if self.jumps.len() > MAX_JUMPS {
+            self.jumps.remove(0);
+        }
+        self.index = self.jumps.len();
+    }
+
+    /// The jump `count` places back from `current`. Going back from the end
+    /// of the list records `current` first, so Ctrl-I can return to it.
+    pub fn back(&mut self, current: Jump, count: usize) -> Option<Jump> {
+        if self.index >= self.jumps.len() {
+            self.push(current);
+            self.index = self.jumps.len() - 1;
+        }
+        self.index = self.index.checked_sub(count)?;
+        Some(self.jumps[self.index])
+    }
+
+    pub fn forward(&mut self, count: usize) -> Option<Jump> {
+        let index = self.index + count;
+        if index >= self.jumps.len() {
+            return None;
+        }
+        self.index = index;
+        Some(self.jumps[index])
+    }
+}
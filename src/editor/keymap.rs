@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+
+use crossterm::event::KeyEvent;
+
+use super::keys;
+
+/// The modes a mapping can apply in, named after the editor modes.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum MapMode {
+    Control,
+    Insert,
+    Visual,
+    OperatorPending,
+    Command,
+}
+
+impl MapMode {
+    const ALL: [MapMode; 5] = [
+        MapMode::Control,
+        MapMode::Visual,
+        MapMode::OperatorPending,
+        MapMode::Insert,
+        MapMode::Command,
+    ];
+
+    /// The modes of a `:map` command, from the letter it starts with: `n`,
+    /// `v`, `o`, `i` or `c`, or none for Control, Visual and Operator-pending
+    /// mode together.
+    pub fn from_prefix(prefix: &str) -> Option<Vec<Self>> {
+        let modes = match prefix {
+            "" => vec![MapMode::Control, MapMode::Visual, MapMode::OperatorPending],
+            "n" => vec![MapMode::Control],
+            "v" | "x" => vec![MapMode::Visual],
+            "o" => vec![MapMode::OperatorPending],
+            "i" => vec![MapMode::Insert],
+            "c" => vec![MapMode::Command],
+            _ => return None,
+        };
+        Some(modes)
+    }
+
+    /// The modes of a keymap table in the config file: `keymap` itself, or
+    /// `keymap.control`, `keymap.insert` and so on.
+    pub fn from_table(table: &str) -> Option<Vec<Self>> {
+        match table {
+            "keymap" => MapMode::from_prefix(""),
+            "keymap.control" => MapMode::from_prefix("n"),
+            "keymap.visual" => MapMode::from_prefix("v"),
+            "keymap.operator" => MapMode::from_prefix("o"),
+            "keymap.insert" => MapMode::from_prefix("i"),
+            "keymap.command" => MapMode::from_prefix("c"),
+            _ => None,
+        }
+    }
+
+    fn letter(self) -> char {
+        match self {
+            MapMode::Control => 'n',
+            MapMode::Visual => 'v',
+            MapMode::OperatorPending => 'o',
+            MapMode::Insert => 'i',
+            MapMode::Command => 'c',
+        }
+    }
+}
+
+/// What a mapping does: runs an editor command, or types other keys, which
+/// aren't mapped again.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Action {
+    Command(&'static str),
+    Keys(Vec<KeyEvent>),
+}
+
+/// The actions a mapping can name instead of spelling out keys.
+const NAMED_COMMANDS: [(&str, &str); 11] = [
+    ("save", "w"),
+    ("save_quit", "wq"),
+    ("quit", "q"),
+    ("quit_all", "qa"),
+    ("force_quit_all", "qa!"),
+    ("next_buffer", "bn"),
+    ("previous_buffer", "bp"),
+    ("list_buffers", "ls"),
+    ("split", "sp"),
+    ("vsplit", "vs"),
+    ("close_window", "close"),
+];
+
+const NAMED_KEYS: [(&str, &str); 13] = [
+    ("left", "h"),
+    ("down", "j"),
+    ("up", "k"),
+    ("right", "l"),
+    ("insert", "i"),
+    ("append", "a"),
+    ("command_line", ":"),
+    ("search", "/"),
+    ("escape", "<Esc>"),
+    ("undo", "u"),
+    ("redo", "<C-r>"),
+    ("put", "p"),
+    ("window", "<C-w>"),
+];
+
+impl Action {
+    /// Reads the right-hand side of a mapping: the name of an action, or
+    /// keys in `<Name>` notation. `<Nop>` does nothing.
+    fn parse(text: &str) -> Self {
+        if let Some((_name, command)) = NAMED_COMMANDS.iter().find(|(name, _)| *name == text) {
+            return Action::Command(command);
+        }
+        if let Some((_name, keys)) = NAMED_KEYS.iter().find(|(name, _)| *name == text) {
+            return Action::Keys(keys::parse(keys));
+        }
+        if text.eq_ignore_ascii_case("<Nop>") {
+            return Action::Keys(Vec::new());
+        }
+        Action::Keys(keys::parse(text))
+    }
+}
+
+struct Mapping {
+    keys: Vec<KeyEvent>,
+    text: String,
+    action: Action,
+}
+
+/// A key to handle as typed, or the action of a mapping whose keys were
+/// typed.
+pub enum Step {
+    Key(KeyEvent),
+    Action(Action),
+}
+
+/// The key mappings of each mode, and the typed keys that may still turn
+/// out to be the start of one.
+pub struct Keymap {
+    tables: HashMap<MapMode, Vec<Mapping>>,
+    pending: Vec<KeyEvent>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let mut keymap = Keymap {
+            tables: HashMap::new(),
+            pending: Vec::new(),
+        };
+        for mode in MapMode::ALL {
+            keymap.map(&[mode], "<C-s>", "save", "");
+            keymap.map(&[mode], "<C-q>", "force_quit_all", "");
+        }
+        keymap
+    }
+}
+
+impl Keymap {
+    /// Maps `lhs` to `rhs` in `modes`, with `<Leader>` in `lhs` standing for
+    /// `leader`.
+    pub fn map(&mut self, modes: &[MapMode], lhs: &str, rhs: &str, leader: &str) {
+        let keys = keys::parse(&expand_leader(lhs, leader));
+        if keys.is_empty() {
+            return;
+        }
+        for mode in modes {
+            let table = self.tables.entry(*mode).or_default();
+            table.retain(|mapping| mapping.keys != keys);
+            table.push(Mapping {
+                keys: keys.clone(),
+                text: String::from(rhs),
+                action: Action::parse(rhs),
+            });
+        }
+    }
+
+    /// Removes the mapping of `lhs` from `modes`, returning whether there
+    /// was one.
+    pub fn unmap(&mut self, modes: &[MapMode], lhs: &str, leader: &str) -> bool {
+        let keys = keys::parse(&expand_leader(lhs, leader));
+        let mut found = false;
+        for mode in modes {
+            if let Some(table) = self.tables.get_mut(mode) {
+                let len = table.len();
+                table.retain(|mapping| mapping.keys != keys);
+                found |= table.len() != len;
+            }
+        }
+        found
+    }
+
+    /// Lists the mappings in `modes` whose keys start with `lhs`, one per
+    /// line.
+    pub fn list(&self, modes: &[MapMode], lhs: &str, leader: &str) -> String {
+        let prefix = keys::parse(&expand_leader(lhs, leader));
+        let mut lines = Vec::new();
+        for mode in MapMode::ALL.iter().filter(|mode| modes.contains(mode)) {
+            for mapping in self.tables.get(mode).into_iter().flatten() {
+                if mapping.keys.starts_with(&prefix) {
+                    let keys = keys::to_notation(&mapping.keys).replace(' ', "<Space>");
+                    lines.push(format!("{}  {keys:<12} {}", mode.letter(), mapping.text));
+                }
+            }
+        }
+        lines.join("\n")
+    }
+
+    pub fn push(&mut self, event: KeyEvent) {
+        self.pending.push(event);
+    }
+
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Sets aside the typed keys, so keys played from a macro are mapped on
+    /// their own.
+    pub fn take_pending(&mut self) -> Vec<KeyEvent> {
+        std::mem::take(&mut self.pending)
+    }
+
+    pub fn restore_pending(&mut self, pending: Vec<KeyEvent>) {
+        self.pending = pending;
+    }
+
+    /// The next thing to do with the typed keys in `mode`, or `None` while
+    /// they are the start of a longer mapping and more keys may still come.
+    /// Without a mode, keys are handled as typed. Once `timed_out`, a
+    /// mapping that matches is used even if a longer one starts with it.
+    pub fn next(&mut self, mode: Option<MapMode>, timed_out: bool) -> Option<Step> {
+        if self.pending.is_empty() {
+            return None;
+        }
+        let Some(table) = mode.and_then(|mode| self.tables.get(&mode)) else {
+            return Some(Step::Key(self.pending.remove(0)));
+        };
+        let longer = table.iter().any(|mapping| {
+            mapping.keys.len() > self.pending.len() && mapping.keys.starts_with(&self.pending)
+        });
+        if longer && !timed_out {
+            return None;
+        }
+        for len in (1..=self.pending.len()).rev() {
+            if let Some(mapping) = table
+                .iter()
+                .find(|mapping| mapping.keys == self.pending[..len])
+            {
+                self.pending.drain(..len);
+                return Some(Step::Action(mapping.action.clone()));
+            }
+        }
+        Some(Step::Key(self.pending.remove(0)))
+    }
+}
+
+/// Replaces `<Leader>`, in any case, with `leader`.
+fn expand_leader(text: &str, leader: &str) -> String {
+    const LEADER: &str = "<leader>";
+    let mut expanded = String::new();
+    let mut rest = text;
+    while let Some(index) = rest.to_ascii_lowercase().find(LEADER) {
+        expanded.push_str(&rest[..index]);
+        expanded.push_str(leader);
+        rest = &rest[index + LEADER.len()..];
+    }
+    expanded.push_str(rest);
+    expanded
+}
@@ -0,0 +1,147 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+const NAMES: [(&str, KeyCode); 13] = [
+    ("Esc", KeyCode::Esc),
+    ("CR", KeyCode::Enter),
+    ("BS", KeyCode::Backspace),
+    ("Del", KeyCode::Delete),
+    ("Tab", KeyCode::Tab),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+];
+
+const MODIFIERS: [(KeyModifiers, &str); 3] = [
+    (KeyModifiers::CONTROL, "C-"),
+    (KeyModifiers::ALT, "M-"),
+    (KeyModifiers::SHIFT, "S-"),
+];
+
+/// Writes keys as text, with keys that aren't plain characters in `<Name>`
+/// notation, such as `<Esc>`, `<F5>` and `<C-r>`, and `<` itself as `<lt>`.
+/// Modifiers are written as `C-`, `M-` and `S-`, as in `<M-x>` and
+/// `<S-Left>`.
+pub fn to_notation(keys: &[KeyEvent]) -> String {
+    let mut text = String::new();
+    for key in keys {
+        let mut modifiers = key.modifiers;
+        let name = match key.code {
+            KeyCode::Char(char) => {
+                let shift = modifiers.contains(KeyModifiers::SHIFT) || char.is_ascii_uppercase();
+                modifiers.remove(KeyModifiers::SHIFT);
+                if modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+                    modifiers.set(KeyModifiers::SHIFT, shift);
+                    match char.to_ascii_lowercase() {
+                        '<' => String::from("lt"),
+                        ' ' => String::from("Space"),
+                        char => char.to_string(),
+                    }
+                } else if char == '<' {
+                    String::from("lt")
+                } else {
+                    text.push(if shift {
+                        char.to_ascii_uppercase()
+                    } else {
+                        char
+                    });
+                    continue;
+                }
+            }
+            KeyCode::BackTab => {
+                modifiers.insert(KeyModifiers::SHIFT);
+                String::from("Tab")
+            }
+            KeyCode::F(number) => format!("F{number}"),
+            code => match NAMES.iter().find(|(_name, known)| *known == code) {
+                Some((name, _code)) => String::from(*name),
+                None => continue,
+            },
+        };
+        text.push('<');
+        for (modifier, prefix) in MODIFIERS {
+            if modifiers.contains(modifier) {
+                text.push_str(prefix);
+            }
+        }
+        text.push_str(&name);
+        text.push('>');
+    }
+    text
+}
+
+/// Reads keys written by `to_notation`, or with `<Space>` for a space and
+/// `<A-x>` for `<M-x>`. A line break is `<CR>`, and a `<` that doesn't start
+/// a key name stands for itself.
+pub fn parse(text: &str) -> Vec<KeyEvent> {
+    let mut keys = Vec::new();
+    let mut rest = text;
+    while let Some(char) = rest.chars().next() {
+        let name = rest
+            .strip_prefix('<')
+            .and_then(|after| after.split_once('>'))
+            .and_then(|(name, after)| Some((parse_name(name)?, after)));
+        if let Some((key, after)) = name {
+            keys.push(key);
+            rest = after;
+            continue;
+        }
+        let code = if char == '\n' {
+            KeyCode::Enter
+        } else {
+            KeyCode::Char(char)
+        };
+        keys.push(KeyEvent::new(code, KeyModifiers::NONE));
+        rest = &rest[char.len_utf8()..];
+    }
+    keys
+}
+
+fn parse_name(name: &str) -> Option<KeyEvent> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut name = name;
+    while let Some((modifier, rest)) = strip_modifier(name) {
+        modifiers.insert(modifier);
+        name = rest;
+    }
+    let mut chars = name.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(_), None) if modifiers.is_empty() => return None,
+        (Some(char), None) if modifiers.contains(KeyModifiers::CONTROL) => {
+            KeyCode::Char(char.to_ascii_lowercase())
+        }
+        (Some(char), None) => KeyCode::Char(char),
+        _ if name.eq_ignore_ascii_case("lt") => KeyCode::Char('<'),
+        _ if name.eq_ignore_ascii_case("space") => KeyCode::Char(' '),
+        _ if name.eq_ignore_ascii_case("tab") && modifiers.contains(KeyModifiers::SHIFT) => {
+            KeyCode::BackTab
+        }
+        _ => match name
+            .strip_prefix(['F', 'f'])
+            .and_then(|digits| digits.parse().ok())
+        {
+            Some(number) if number > 0 => KeyCode::F(number),
+            _ => NAMES
+                .iter()
+                .find(|(known, _code)| known.eq_ignore_ascii_case(name))
+                .map(|(_name, code)| *code)?,
+        },
+    };
+    Some(KeyEvent::new(code, modifiers))
+}
+
+/// Splits a `C-`, `M-` (or `A-`) or `S-` prefix off a key name.
+fn strip_modifier(name: &str) -> Option<(KeyModifiers, &str)> {
+    let rest = name.get(2..).filter(|rest| !rest.is_empty())?;
+    let modifier = match name.get(..2)?.to_ascii_uppercase().as_str() {
+        "C-" => KeyModifiers::CONTROL,
+        "M-" | "A-" => KeyModifiers::ALT,
+        "S-" => KeyModifiers::SHIFT,
+        _ => return None,
+    };
+    Some((modifier, rest))
+}
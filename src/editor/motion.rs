@@ -0,0 +1,459 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::buffer::Buffer;
+
+/// A search for a character on the cursor line, as done by `f`, `F`, `t` and
+/// `T`. `till` stops one grapheme short of the character.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Find {
+    pub char: char,
+    pub forward: bool,
+    pub till: bool,
+}
+
+impl Find {
+    pub fn reversed(self) -> Self {
+        Find {
+            forward: !self.forward,
+            ..self
+        }
+    }
+}
+
+/// How much text a motion covers when an operator is applied over it.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    Exclusive,
+    Inclusive,
+    Linewise,
+}
+
+/// The first buffer line shown in the window and how many lines are shown,
+/// which `H`, `M` and `L` move within.
+#[derive(Clone, Copy)]
+pub struct Viewport {
+    pub top: usize,
+    pub lines: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Motion {
+    Left,
+    Down,
+    Up,
+    Right,
+    WordForward { big: bool },
+    WordBackward { big: bool },
+    WordEnd { big: bool },
+    LineStart,
+    FirstNonBlank,
+    LineEnd,
+    FirstLine,
+    LastLine,
+    ParagraphForward,
+    ParagraphBackward,
+    Find(Find),
+    ScreenTop,
+    ScreenMiddle,
+    ScreenBottom,
+    MatchingBracket,
+    Mark { name: char, linewise: bool },
+}
+
+impl Motion {
+    pub fn kind(self) -> Kind {
+        match self {
+            Motion::Down
+            | Motion::Up
+            | Motion::FirstLine
+            | Motion::LastLine
+            | Motion::ScreenTop
+            | Motion::ScreenMiddle
+            | Motion::ScreenBottom
+            | Motion::Mark { linewise: true, .. } => Kind::Linewise,
+            Motion::WordEnd { .. } | Motion::LineEnd | Motion::MatchingBracket => Kind::Inclusive,
+            Motion::Find(find) if find.forward => Kind::Inclusive,
+            _ => Kind::Exclusive,
+        }
+    }
+
+    /// Whether the motion is a big jump that the jump list records.
+    pub fn is_jump(self) -> bool {
+        matches!(
+            self,
+            Motion::FirstLine | Motion::LastLine | Motion::Mark { .. }
+        )
+    }
+
+    /// Where the motion moves the cursor from `(x, y)`, or `None` if it
+    /// fails. The column may be one past the end of the line, which callers
+    /// outside Insert mode should clamp.
+    pub fn target(
+        self,
+        buffer: &Buffer,
+        (x, y): (usize, usize),
+        count: Option<usize>,
+        viewport: Viewport,
+    ) -> Option<(usize, usize)> {
+        let times = count.unwrap_or(1);
+        let last_line = buffer.len().saturating_sub(1);
+        let repeat = |motion: fn(&Buffer, (usize, usize)) -> (usize, usize)| {
+            (0..times).fold((x, y), |position, _| motion(buffer, position))
+        };
+        let target = match self {
+            Motion::Left => (x.saturating_sub(times), y),
+            Motion::Right => (usize::min(x + times, buffer.line_len(y)), y),
+            Motion::Down if y < last_line => (x, usize::min(y + times, last_line)),
+            Motion::Up if y > 0 => (x, y.saturating_sub(times)),
+            Motion::Down | Motion::Up => return None,
+            Motion::WordForward { big: false } => repeat(|b, p| word_forward(b, p, false)),
+            Motion::WordForward { big: true } => repeat(|b, p| word_forward(b, p, true)),
+            Motion::WordBackward { big: false } => repeat(|b, p| word_backward(b, p, false)),
+            Motion::WordBackward { big: true } => repeat(|b, p| word_backward(b, p, true)),
+            Motion::WordEnd { big: false } => repeat(|b, p| word_end(b, p, false)),
+            Motion::WordEnd { big: true } => repeat(|b, p| word_end(b, p, true)),
+            Motion::LineStart => (0, y),
+            Motion::FirstNonBlank => (first_non_blank(buffer, y), y),
+            Motion::LineEnd => {
+                let y = usize::min(y + times - 1, last_line);
+                (buffer.line_len(y).saturating_sub(1), y)
+            }
+            Motion::FirstLine | Motion::LastLine => {
+                let default = if self == Motion::FirstLine {
+                    1
+                } else {
+                    buffer.len()
+                };
+                let y = usize::min(count.unwrap_or(default).saturating_sub(1), last_line);
+                (first_non_blank(buffer, y), y)
+            }
+            Motion::ParagraphForward => repeat(paragraph_forward),
+            Motion::ParagraphBackward => repeat(paragraph_backward),
+            Motion::Find(find) => (find_char(buffer, (x, y), find, times)?, y),
+            Motion::ScreenTop | Motion::ScreenMiddle | Motion::ScreenBottom => {
+                let lines = usize::max(viewport.lines, 1);
+                let offset = usize::min(times - 1, lines - 1);
+                let y = viewport.top
+                    + match self {
+                        Motion::ScreenTop => offset,
+                        Motion::ScreenMiddle => (lines - 1) / 2,
+                        _ => lines - 1 - offset,
+                    };
+                let y = usize::min(y, last_line);
+                (first_non_blank(buffer, y), y)
+            }
+            Motion::MatchingBracket => matching_bracket(buffer, (x, y))?,
+            Motion::Mark { name, linewise } => {
+                let (x, y) = buffer.mark(name)?;
+                let y = usize::min(y, last_line);
+                if linewise {
+                    (first_non_blank(buffer, y), y)
+                } else {
+                    (x, y)
+                }
+            }
+        };
+        Some(target)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Blank,
+    EmptyLine,
+    Word,
+    Punctuation,
+}
+
+fn class(grapheme: &str, big: bool) -> Class {
+    let char = grapheme.chars().next().unwrap_or(' ');
+    if char.is_whitespace() {
+        Class::Blank
+    } else if big || char.is_alphanumeric() || char == '_' {
+        Class::Word
+    } else {
+        Class::Punctuation
+    }
+}
+
+fn graphemes(buffer: &Buffer, y: usize) -> Vec<String> {
+    buffer
+        .get_line(y)
+        .unwrap_or_default()
+        .graphemes(true)
+        .map(String::from)
+        .collect()
+}
+
+/// Steps through a buffer one grapheme at a time. The column one past the
+/// end of each line stands for its line break, so lines never run together.
+struct Walker<'a> {
+    buffer: &'a Buffer,
+    x: usize,
+    y: usize,
+    line: Vec<String>,
+    big: bool,
+}
+
+impl<'a> Walker<'a> {
+    fn new(buffer: &'a Buffer, (x, y): (usize, usize), big: bool) -> Self {
+        Walker {
+            buffer,
+            x,
+            y,
+            line: graphemes(buffer, y),
+            big,
+        }
+    }
+
+    fn position(&self) -> (usize, usize) {
+        (self.x, self.y)
+    }
+
+    fn grapheme(&self) -> Option<&str> {
+        self.line.get(self.x).map(String::as_str)
+    }
+
+    fn class(&self) -> Class {
+        match self.grapheme() {
+            Some(grapheme) => class(grapheme, self.big),
+            None if self.line.is_empty() => Class::EmptyLine,
+            None => Class::Blank,
+        }
+    }
+
+    fn next(&mut self) -> bool {
+        if self.x < self.line.len() {
+            self.x += 1;
+        } else if self.y + 1 < self.buffer.len() {
+            self.y += 1;
+            self.x = 0;
+            self.line = graphemes(self.buffer, self.y);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    fn prev(&mut self) -> bool {
+        if self.x > 0 {
+            self.x -= 1;
+        } else if self.y > 0 {
+            self.y -= 1;
+            self.line = graphemes(self.buffer, self.y);
+            self.x = self.line.len();
+        } else {
+            return false;
+        }
+        true
+    }
+}
+
+pub fn word_forward(buffer: &Buffer, position: (usize, usize), big: bool) -> (usize, usize) {
+    let mut walker = Walker::new(buffer, position, big);
+    let start = walker.class();
+    match start {
+        Class::Word | Class::Punctuation => {
+            while walker.class() == start {
+                if !walker.next() {
+                    return walker.position();
+                }
+            }
+        }
+        Class::EmptyLine => {
+            if !walker.next() {
+                return position;
+            }
+        }
+        Class::Blank => (),
+    }
+    while walker.class() == Class::Blank {
+        if !walker.next() {
+            return walker.position();
+        }
+    }
+    walker.position()
+}
+
+pub fn word_backward(buffer: &Buffer, position: (usize, usize), big: bool) -> (usize, usize) {
+    let mut walker = Walker::new(buffer, position, big);
+    if !walker.prev() {
+        return position;
+    }
+    while walker.class() == Class::Blank {
+        if !walker.prev() {
+            return walker.position();
+        }
+    }
+    let start = walker.class();
+    if start == Class::EmptyLine {
+        return walker.position();
+    }
+    while walker.x > 0 && class(&walker.line[walker.x - 1], big) == start {
+        walker.x -= 1;
+    }
+    walker.position()
+}
+
+pub fn word_end(buffer: &Buffer, position: (usize, usize), big: bool) -> (usize, usize) {
+    let mut walker = Walker::new(buffer, position, big);
+    if !walker.next() {
+        return position;
+    }
+    while matches!(walker.class(), Class::Blank | Class::EmptyLine) {
+        if !walker.next() {
+            return position;
+        }
+    }
+    let start = walker.class();
+    while walker
+        .line
+        .get(walker.x + 1)
+        .is_some_and(|grapheme| class(grapheme, big) == start)
+    {
+        walker.x += 1;
+    }
+    walker.position()
+}
+
+/// Where `cw` stops: the end of the word under the cursor rather than the
+/// start of the next one, or `None` if the cursor is on a blank.
+pub fn change_word_end(
+    buffer: &Buffer,
+    (x, y): (usize, usize),
+    big: bool,
+    count: usize,
+) -> Option<(usize, usize)> {
+    let line = graphemes(buffer, y);
+    let start = class(line.get(x)?, big);
+    if start == Class::Blank {
+        return None;
+    }
+    let length = line[x..]
+        .iter()
+        .take_while(|grapheme| class(grapheme, big) == start)
+        .count();
+    Some((1..count).fold((x + length - 1, y), |position, _| {
+        word_end(buffer, position, big)
+    }))
+}
+
+/// Where `w` stops after an operator: like `w`, except that when the last
+/// word moved over ends its line, the end of that line rather than the start
+/// of the next.
+pub fn operator_word_forward(
+    buffer: &Buffer,
+    position: (usize, usize),
+    big: bool,
+    count: usize,
+) -> (usize, usize) {
+    let last = (1..count).fold(position, |position, _| word_forward(buffer, position, big));
+    let target = word_forward(buffer, last, big);
+    let line_len = buffer.line_len(last.1);
+    if target.1 > last.1 && line_len > 0 {
+        (line_len, last.1)
+    } else {
+        target
+    }
+}
+
+pub fn first_non_blank(buffer: &Buffer, y: usize) -> usize {
+    let line = graphemes(buffer, y);
+    line.iter()
+        .position(|grapheme| class(grapheme, false) != Class::Blank)
+        .unwrap_or(line.len().saturating_sub(1))
+}
+
+pub fn paragraph_forward(buffer: &Buffer, (_x, y): (usize, usize)) -> (usize, usize) {
+    let mut y = y;
+    while y + 1 < buffer.len() && buffer.line_len(y) == 0 {
+        y += 1;
+    }
+    while y + 1 < buffer.len() {
+        y += 1;
+        if buffer.line_len(y) == 0 {
+            return (0, y);
+        }
+    }
+    (buffer.line_len(y), y)
+}
+
+pub fn paragraph_backward(buffer: &Buffer, (_x, y): (usize, usize)) -> (usize, usize) {
+    let mut y = y;
+    while y > 0 && buffer.line_len(y) == 0 {
+        y -= 1;
+    }
+    while y > 0 {
+        y -= 1;
+        if buffer.line_len(y) == 0 {
+            return (0, y);
+        }
+    }
+    (0, 0)
+}
+
+/// The column of the `count`th match of `find` on line `y`, measured from
+/// column `x`.
+pub fn find_char(
+    buffer: &Buffer,
+    (x, y): (usize, usize),
+    find: Find,
+    count: usize,
+) -> Option<usize> {
+    let line = graphemes(buffer, y);
+    let is_match = |column: &usize| line[*column].starts_with(find.char);
+    let column = if find.forward {
+        (x + 1..line.len()).filter(is_match).nth(count - 1)?
+    } else {
+        (0..x).rev().filter(is_match).nth(count - 1)?
+    };
+    Some(match (find.till, find.forward) {
+        (false, _) => column,
+        (true, true) => column - 1,
+        (true, false) => column + 1,
+    })
+}
+
+/// The bracket matching the one under the cursor, or the first one after
+/// the cursor on its line.
+pub fn matching_bracket(buffer: &Buffer, (x, y): (usize, usize)) -> Option<(usize, usize)> {
+    const PAIRS: [(&str, &str); 3] = [("(", ")"), ("[", "]"), ("{", "}")];
+    let mut walker = Walker::new(buffer, (x, y), false);
+    let (offset, open, close, forward) =
+        walker
+            .line
+            .get(x..)?
+            .iter()
+            .enumerate()
+            .find_map(|(offset, grapheme)| {
+                PAIRS.iter().find_map(|&(open, close)| {
+                    if grapheme == open {
+                        Some((offset, open, close, true))
+                    } else if grapheme == close {
+                        Some((offset, close, open, false))
+                    } else {
+                        None
+                    }
+                })
+            })?;
+    walker.x = x + offset;
+    let mut depth = 0;
+    loop {
+        match walker.grapheme() {
+            Some(grapheme) if grapheme == open => depth += 1,
+            Some(grapheme) if grapheme == close => depth -= 1,
+            _ => (),
+        }
+        if depth == 0 {
+            return Some(walker.position());
+        }
+        let moved = if forward {
+            walker.next()
+        } else {
+            walker.prev()
+        };
+        if !moved {
+            return None;
+        }
+    }
+}
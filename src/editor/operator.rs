@@ -0,0 +1,287 @@
+use super::buffer::Buffer;
+use super::motion::{self, Kind};
+use super::options::Options;
+use super::register::Register;
+use super::selection::Shape;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Operator {
+    Delete,
+    Change,
+    Yank,
+    Indent,
+    Dedent,
+    Lowercase,
+    Uppercase,
+    ToggleCase,
+}
+
+impl Operator {
+    pub fn from_char(char: char) -> Option<Self> {
+        match char {
+            'd' => Some(Operator::Delete),
+            'c' => Some(Operator::Change),
+            'y' => Some(Operator::Yank),
+            '>' => Some(Operator::Indent),
+            '<' => Some(Operator::Dedent),
+            _ => None,
+        }
+    }
+
+    /// The case operators, typed after `g` in Control mode and on their own
+    /// in Visual mode.
+    pub fn from_case_char(char: char) -> Option<Self> {
+        match char {
+            'u' => Some(Operator::Lowercase),
+            'U' => Some(Operator::Uppercase),
+            '~' => Some(Operator::ToggleCase),
+            _ => None,
+        }
+    }
+
+    pub fn char(self) -> char {
+        match self {
+            Operator::Delete => 'd',
+            Operator::Change => 'c',
+            Operator::Yank => 'y',
+            Operator::Indent => '>',
+            Operator::Dedent => '<',
+            Operator::Lowercase => 'u',
+            Operator::Uppercase => 'U',
+            Operator::ToggleCase => '~',
+        }
+    }
+
+    fn convert_case(self, text: &str) -> String {
+        match self {
+            Operator::Lowercase => text.to_lowercase(),
+            Operator::Uppercase => text.to_uppercase(),
+            _ => text
+                .chars()
+                .map(|char| {
+                    if char.is_lowercase() {
+                        char.to_uppercase().to_string()
+                    } else {
+                        char.to_lowercase().to_string()
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// The text an operator acts on: whole lines `start..=end`, the characters
+/// from one position up to but not including another, or a block from a
+/// top-left corner up to but not including the column of its bottom-right
+/// one.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Range {
+    Lines(usize, usize),
+    Characters((usize, usize), (usize, usize)),
+    Block((usize, usize), (usize, usize)),
+}
+
+impl Range {
+    /// The range a motion from `cursor` to `target` covers. An exclusive
+    /// motion that ends in the first column stops at the end of the line
+    /// before, and covers whole lines if it also started at the indent.
+    pub fn new(
+        buffer: &Buffer,
+        cursor: (usize, usize),
+        target: (usize, usize),
+        kind: Kind,
+    ) -> Self {
+        let (start, end) = if (target.1, target.0) < (cursor.1, cursor.0) {
+            (target, cursor)
+        } else {
+            (cursor, target)
+        };
+        match kind {
+            Kind::Linewise => Range::Lines(start.1, end.1),
+            Kind::Inclusive => Range::Characters(
+                start,
+                (usize::min(end.0 + 1, buffer.line_len(end.1)), end.1),
+            ),
+            Kind::Exclusive if end.0 == 0 && end.1 > start.1 => {
+                if start.0 <= motion::first_non_blank(buffer, start.1) {
+                    Range::Lines(start.1, end.1 - 1)
+                } else {
+                    Range::Characters(start, (buffer.line_len(end.1 - 1), end.1 - 1))
+                }
+            }
+            Kind::Exclusive => Range::Characters(start, end),
+        }
+    }
+
+    fn rows(self) -> (usize, usize) {
+        match self {
+            Range::Lines(start, end) => (start, end),
+            Range::Characters(start, end) | Range::Block(start, end) => (start.1, end.1),
+        }
+    }
+
+    /// The grapheme columns of line `row` the range covers. An empty line
+    /// within the range counts as one column.
+    pub fn columns(self, row: usize, line_len: usize) -> Option<std::ops::Range<usize>> {
+        let (start_row, end_row) = self.rows();
+        if row < start_row || row > end_row {
+            return None;
+        }
+        let columns = match self {
+            Range::Lines(..) => 0..usize::max(line_len, 1),
+            Range::Characters(start, end) => {
+                let from = if row == start.1 { start.0 } else { 0 };
+                let to = if row == end.1 {
+                    end.0
+                } else {
+                    usize::max(line_len, 1)
+                };
+                from..to
+            }
+            Range::Block(start, end) => start.0..end.0,
+        };
+        (!columns.is_empty()).then_some(columns)
+    }
+
+    /// The columns of each line of a block, clipped to the line.
+    fn block_columns(
+        buffer: &Buffer,
+        (left, top): (usize, usize),
+        (right, bottom): (usize, usize),
+    ) -> Vec<(usize, usize, usize)> {
+        (top..=bottom)
+            .map(|row| {
+                let line_len = buffer.line_len(row);
+                (row, usize::min(left, line_len), usize::min(right, line_len))
+            })
+            .collect()
+    }
+}
+
+impl Operator {
+    /// Applies the operator to `range`, returning the text it removed or
+    /// yanked and where the cursor should go. A change leaves the cursor
+    /// where the replacement text is typed.
+    pub fn apply(
+        self,
+        buffer: &mut Buffer,
+        range: Range,
+        cursor: (usize, usize),
+        options: &Options,
+    ) -> Result<(Option<Register>, (usize, usize)), std::io::Error> {
+        let (start_row, end_row) = range.rows();
+        let register = match range {
+            Range::Lines(start, end) => Register {
+                text: buffer.lines_text(start, end),
+                shape: Shape::Lines,
+            },
+            Range::Characters(start, end) => Register {
+                text: buffer.text_range(start, end),
+                shape: Shape::Characters,
+            },
+            Range::Block(start, end) => Register {
+                text: Range::block_columns(buffer, start, end)
+                    .into_iter()
+                    .map(|(row, left, right)| buffer.text_range((left, row), (right, row)))
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                shape: Shape::Block,
+            },
+        };
+        let cursor = match (self, range) {
+            (Operator::Yank, Range::Lines(start, _)) => (cursor.0, start),
+            (Operator::Yank, Range::Characters(start, _) | Range::Block(start, _)) => start,
+            (Operator::Delete | Operator::Change, Range::Characters(start, end)) => {
+                buffer.delete_range(start, end)?;
+                start
+            }
+            (Operator::Delete, Range::Lines(start, end)) => {
+                buffer.delete_lines(start, end)?;
+                let row = usize::min(start, buffer.len() - 1);
+                (motion::first_non_blank(buffer, row), row)
+            }
+            (Operator::Change, Range::Lines(start, end)) => {
+                buffer.begin_undo_group();
+                if end > start {
+                    buffer.delete_lines(start + 1, end)?;
+                }
+                buffer.delete_range((0, start), (buffer.line_len(start), start))?;
+                buffer.end_undo_group();
+                (0, start)
+            }
+            (Operator::Delete | Operator::Change, Range::Block(start, end)) => {
+                buffer.begin_undo_group();
+                for (row, left, right) in Range::block_columns(buffer, start, end) {
+                    if left < right {
+                        buffer.delete_range((left, row), (right, row))?;
+                    }
+                }
+                buffer.end_undo_group();
+                start
+            }
+            (Operator::Indent | Operator::Dedent, _) => {
+                buffer.begin_undo_group();
+                for row in start_row..=end_row {
+                    if self == Operator::Indent {
+                        buffer.indent_line(row, &options.indent())?;
+                    } else {
+                        buffer.dedent_line(row, options.tab_stop)?;
+                    }
+                }
+                buffer.end_undo_group();
+                (motion::first_non_blank(buffer, start_row), start_row)
+            }
+            (Operator::Lowercase | Operator::Uppercase | Operator::ToggleCase, _) => {
+                buffer.begin_undo_group();
+                for row in start_row..=end_row {
+                    let Some(columns) = range.columns(row, buffer.line_len(row)) else {
+                        continue;
+                    };
+                    let start = (columns.start, row);
+                    let end = (usize::min(columns.end, buffer.line_len(row)), row);
+                    let text = buffer.text_range(start, end);
+                    let converted = self.convert_case(&text);
+                    if converted != text {
+                        buffer.delete_range(start, end)?;
+                        buffer.insert_text(start, &converted)?;
+                    }
+                }
+                buffer.end_undo_group();
+                match range {
+                    Range::Lines(start, _) => (cursor.0, start),
+                    Range::Characters(start, _) | Range::Block(start, _) => start,
+                }
+            }
+        };
+        let register = matches!(self, Operator::Delete | Operator::Change | Operator::Yank)
+            .then_some(register);
+        Ok((register, cursor))
+    }
+
+    /// The message shown after the operator changed or yanked more than
+    /// two lines.
+    pub fn report(self, range: Range, register: Option<&Register>) -> Option<String> {
+        let (start_row, end_row) = range.rows();
+        let lines = end_row - start_row + 1;
+        let removed = match (range, register) {
+            (Range::Characters(..), Some(register)) => register.text.matches('\n').count(),
+            (Range::Block(..), _) => 0,
+            _ => lines,
+        };
+        let message = match self {
+            Operator::Yank if lines > 2 && matches!(range, Range::Block(..)) => {
+                format!("block of {lines} lines yanked")
+            }
+            Operator::Yank if lines > 2 => format!("{lines} lines yanked"),
+            Operator::Delete | Operator::Change if removed > 2 => format!("{removed} fewer lines"),
+            Operator::Indent | Operator::Dedent if lines > 2 => {
+                format!("{lines} lines {}ed 1 time", self.char())
+            }
+            Operator::Lowercase | Operator::Uppercase | Operator::ToggleCase if lines > 2 => {
+                format!("{lines} lines changed")
+            }
+            _ => return None,
+        };
+        Some(message)
+    }
+}
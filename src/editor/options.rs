@@ -0,0 +1,240 @@
+use crossterm::cursor::SetCursorStyle;
+
+use super::display;
+use super::gutter::LineNumbers;
+use super::highlight::Theme;
+
+/// A value an option can be set to, from the config file or `:set`.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Value {
+    Bool(bool),
+    Number(usize),
+    Text(String),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum CursorShape {
+    Block,
+    Bar,
+    Underline,
+}
+
+impl CursorShape {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "block" => Some(CursorShape::Block),
+            "bar" => Some(CursorShape::Bar),
+            "underline" => Some(CursorShape::Underline),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            CursorShape::Block => "block",
+            CursorShape::Bar => "bar",
+            CursorShape::Underline => "underline",
+        }
+    }
+
+    pub fn style(self, blink: bool) -> SetCursorStyle {
+        match (self, blink) {
+            (CursorShape::Block, true) => SetCursorStyle::BlinkingBlock,
+            (CursorShape::Block, false) => SetCursorStyle::SteadyBlock,
+            (CursorShape::Bar, true) => SetCursorStyle::BlinkingBar,
+            (CursorShape::Bar, false) => SetCursorStyle::SteadyBar,
+            (CursorShape::Underline, true) => SetCursorStyle::BlinkingUnderScore,
+            (CursorShape::Underline, false) => SetCursorStyle::SteadyUnderScore,
+        }
+    }
+}
+
+/// Short names `:set` accepts in place of the full option names.
+const ABBREVIATIONS: [(&str, &str); 8] = [
+    ("ts", "tabstop"),
+    ("et", "expandtab"),
+    ("nu", "number"),
+    ("rnu", "relativenumber"),
+    ("lbr", "linebreak"),
+    ("so", "scrolloff"),
+    ("cb", "clipboard"),
+    ("tm", "timeoutlen"),
+];
+
+/// The settings that can be changed from the config file and with `:set`.
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub tab_stop: usize,
+    pub expand_tab: bool,
+    pub number: bool,
+    pub relative_number: bool,
+    pub wrap: bool,
+    pub linebreak: bool,
+    pub scroll_off: usize,
+    pub clipboard: bool,
+    pub cursor_blink: bool,
+    pub control_cursor: CursorShape,
+    pub insert_cursor: CursorShape,
+    pub visual_cursor: CursorShape,
+    pub command_cursor: CursorShape,
+    pub pending_cursor: CursorShape,
+    pub theme: Theme,
+    pub filler: String,
+    pub leader: String,
+    pub timeout_len: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            tab_stop: 4,
+            expand_tab: true,
+            number: false,
+            relative_number: false,
+            wrap: false,
+            linebreak: false,
+            scroll_off: 0,
+            clipboard: false,
+            cursor_blink: true,
+            control_cursor: CursorShape::Block,
+            insert_cursor: CursorShape::Bar,
+            visual_cursor: CursorShape::Block,
+            command_cursor: CursorShape::Bar,
+            pending_cursor: CursorShape::Underline,
+            theme: Theme::Default,
+            filler: String::from("~"),
+            leader: String::from("\\"),
+            timeout_len: 1000,
+        }
+    }
+}
+
+impl Options {
+    pub fn line_numbers(&self) -> LineNumbers {
+        LineNumbers::from_flags(self.number, self.relative_number)
+    }
+
+    /// The text `>` adds in front of a line.
+    pub fn indent(&self) -> String {
+        if self.expand_tab {
+            " ".repeat(self.tab_stop)
+        } else {
+            String::from("\t")
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Result<Value, String> {
+        let value = match full_name(name) {
+            "tabstop" => Value::Number(self.tab_stop),
+            "expandtab" => Value::Bool(self.expand_tab),
+            "number" => Value::Bool(self.number),
+            "relativenumber" => Value::Bool(self.relative_number),
+            "wrap" => Value::Bool(self.wrap),
+            "linebreak" => Value::Bool(self.linebreak),
+            "scrolloff" => Value::Number(self.scroll_off),
+            "clipboard" => Value::Bool(self.clipboard),
+            "cursorblink" => Value::Bool(self.cursor_blink),
+            "controlcursor" => Value::Text(String::from(self.control_cursor.name())),
+            "insertcursor" => Value::Text(String::from(self.insert_cursor.name())),
+            "visualcursor" => Value::Text(String::from(self.visual_cursor.name())),
+            "commandcursor" => Value::Text(String::from(self.command_cursor.name())),
+            "pendingcursor" => Value::Text(String::from(self.pending_cursor.name())),
+            "theme" => Value::Text(String::from(self.theme.name())),
+            "filler" => Value::Text(self.filler.clone()),
+            "leader" => Value::Text(self.leader.clone()),
+            "timeoutlen" => Value::Number(self.timeout_len),
+            _ => return Err(format!("Unknown option: {name}")),
+        };
+        Ok(value)
+    }
+
+    pub fn set(&mut self, name: &str, value: Value) -> Result<(), String> {
+        let invalid = || format!("Invalid value for {name}");
+        let cursor = |value: &str| CursorShape::from_name(value).ok_or_else(invalid);
+        match (full_name(name), value) {
+            ("tabstop", Value::Number(tab_stop)) if tab_stop > 0 => self.tab_stop = tab_stop,
+            ("expandtab", Value::Bool(expand_tab)) => self.expand_tab = expand_tab,
+            ("number", Value::Bool(number)) => self.number = number,
+            ("relativenumber", Value::Bool(relative)) => self.relative_number = relative,
+            ("wrap", Value::Bool(wrap)) => self.wrap = wrap,
+            ("linebreak", Value::Bool(linebreak)) => self.linebreak = linebreak,
+            ("scrolloff", Value::Number(scroll_off)) => self.scroll_off = scroll_off,
+            ("clipboard", Value::Bool(clipboard)) => self.clipboard = clipboard,
+            ("cursorblink", Value::Bool(blink)) => self.cursor_blink = blink,
+            ("controlcursor", Value::Text(shape)) => self.control_cursor = cursor(&shape)?,
+            ("insertcursor", Value::Text(shape)) => self.insert_cursor = cursor(&shape)?,
+            ("visualcursor", Value::Text(shape)) => self.visual_cursor = cursor(&shape)?,
+            ("commandcursor", Value::Text(shape)) => self.command_cursor = cursor(&shape)?,
+            ("pendingcursor", Value::Text(shape)) => self.pending_cursor = cursor(&shape)?,
+            ("theme", Value::Text(theme)) => {
+                self.theme = Theme::from_name(&theme).ok_or_else(invalid)?;
+            }
+            ("filler", Value::Text(filler)) if display::width(&filler) == 1 => {
+                self.filler = filler;
+            }
+            ("leader", Value::Text(leader)) if !leader.is_empty() => self.leader = leader,
+            ("timeoutlen", Value::Number(timeout_len)) => self.timeout_len = timeout_len,
+            _ => {
+                self.get(name)?;
+                return Err(invalid());
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies one argument of `:set`: `name` turns a boolean option on,
+    /// `noname` turns it off and `name!` toggles it, `name=value` sets any
+    /// option and `name?` asks for its value. Returns the text to show for a
+    /// query.
+    pub fn apply(&mut self, argument: &str) -> Result<Option<String>, String> {
+        if let Some(name) = argument.strip_suffix('?') {
+            return Ok(Some(self.show(name)?));
+        }
+        if let Some((name, text)) = argument.split_once('=') {
+            let value = match self.get(name)? {
+                Value::Number(_) => Value::Number(
+                    text.parse()
+                        .map_err(|_| format!("Number required: {argument}"))?,
+                ),
+                Value::Text(_) => Value::Text(String::from(text)),
+                Value::Bool(_) => return Err(format!("Invalid argument: {argument}")),
+            };
+            self.set(name, value)?;
+            return Ok(None);
+        }
+        if let Some(name) = argument.strip_suffix('!') {
+            return match self.get(name)? {
+                Value::Bool(value) => self.set(name, Value::Bool(!value)).map(|()| None),
+                _ => Err(format!("Invalid argument: {argument}")),
+            };
+        }
+        match self.get(argument) {
+            Ok(Value::Bool(_)) => self.set(argument, Value::Bool(true)).map(|()| None),
+            Ok(_) => Ok(Some(self.show(argument)?)),
+            Err(message) => match argument.strip_prefix("no") {
+                Some(name) if matches!(self.get(name), Ok(Value::Bool(_))) => {
+                    self.set(name, Value::Bool(false)).map(|()| None)
+                }
+                _ => Err(message),
+            },
+        }
+    }
+
+    fn show(&self, name: &str) -> Result<String, String> {
+        let name = full_name(name);
+        Ok(match self.get(name)? {
+            Value::Bool(true) => format!("  {name}"),
+            Value::Bool(false) => format!("no{name}"),
+            Value::Number(number) => format!("  {name}={number}"),
+            Value::Text(text) => format!("  {name}={text}"),
+        })
+    }
+}
+
+fn full_name(name: &str) -> &str {
+    ABBREVIATIONS
+        .iter()
+        .find(|(short, _full)| *short == name)
+        .map_or(name, |(_short, full)| full)
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use super::selection::Shape;
+
+/// Text held for putting back later, remembering whether it was taken as
+/// characters, whole lines or a block.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Register {
+    pub text: String,
+    pub shape: Shape,
+}
+
+impl Register {
+    /// The lines of a linewise or blockwise register, without the final line
+    /// break.
+    pub fn lines(&self) -> Vec<&str> {
+        let text = self.text.strip_suffix('\n').unwrap_or(&self.text);
+        text.split('\n').collect()
+    }
+}
+
+pub const UNNAMED: char = '"';
+pub const CLIPBOARD: char = '+';
+const BLACK_HOLE: char = '_';
+const LAST_YANK: char = '0';
+const SMALL_DELETE: char = '-';
+
+pub fn is_valid(name: char) -> bool {
+    name.is_ascii_alphanumeric() || [UNNAMED, CLIPBOARD, BLACK_HOLE, SMALL_DELETE].contains(&name)
+}
+
+/// The registers yanked and deleted text goes to. The unnamed register always
+/// holds the latest text, `0` the latest yank, `1`–`9` the latest deletes of
+/// whole or several lines and `-` the latest smaller delete. Writing to an
+/// uppercase name appends to the lowercase register.
+#[derive(Default)]
+pub struct Registers {
+    registers: HashMap<char, Register>,
+}
+
+impl Registers {
+    pub fn get(&self, name: Option<char>) -> Option<&Register> {
+        self.registers.get(&name.unwrap_or(UNNAMED))
+    }
+
+    /// Stores `register` under `name` only, leaving the unnamed register
+    /// alone.
+    pub fn set(&mut self, name: char, register: Register) {
+        if name == BLACK_HOLE {
+            return;
+        }
+        if !name.is_ascii_uppercase() {
+            self.registers.insert(name, register);
+            return;
+        }
+        let name = name.to_ascii_lowercase();
+        match self.registers.get_mut(&name) {
+            Some(existing) => {
+                let charwise = register.shape == Shape::Characters;
+                if (!charwise || existing.shape != Shape::Characters)
+                    && !existing.text.ends_with('\n')
+                {
+                    existing.text.push('\n');
+                }
+                existing.text.push_str(&register.text);
+                if existing.shape == Shape::Characters || register.shape == Shape::Lines {
+                    existing.shape = register.shape;
+                }
+            }
+            None => {
+                self.registers.insert(name, register);
+            }
+        }
+    }
+
+    pub fn yank(&mut self, name: Option<char>, register: Register) {
+        match name {
+            Some(BLACK_HOLE) => (),
+            Some(name) => self.store(name, register),
+            None => self.store(LAST_YANK, register),
+        }
+    }
+
+    pub fn delete(&mut self, name: Option<char>, register: Register) {
+        match name {
+            Some(BLACK_HOLE) => (),
+            Some(name) => self.store(name, register),
+            None if register.shape == Shape::Lines || register.text.contains('\n') => {
+                for number in (1..9).rev() {
+                    let from = char::from_digit(number, 10).unwrap_or(LAST_YANK);
+                    let to = char::from_digit(number + 1, 10).unwrap_or(LAST_YANK);
+                    if let Some(shifted) = self.registers.remove(&from) {
+                        self.registers.insert(to, shifted);
+                    }
+                }
+                self.store('1', register);
+            }
+            None => self.store(SMALL_DELETE, register),
+        }
+    }
+
+    fn store(&mut self, name: char, register: Register) {
+        self.set(name, register);
+        let name = name.to_ascii_lowercase();
+        if let Some(register) = self.registers.get(&name).cloned() {
+            self.registers.insert(UNNAMED, register);
+        }
+    }
+}
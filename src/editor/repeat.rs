@@ -0,0 +1,89 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// The keys of a command that changed the buffer, without the digits of its
+/// count, so `.` can replay it with the same or a new count.
+#[derive(Clone)]
+pub struct Change {
+    keys: Vec<KeyEvent>,
+    count: Option<usize>,
+}
+
+impl Change {
+    /// The keys to replay, led by the digits of `count`, or of the original
+    /// count if there is none.
+    pub fn keys(&self, count: Option<usize>) -> Vec<KeyEvent> {
+        let digits = count
+            .or(self.count)
+            .map(|count| count.to_string())
+            .unwrap_or_default();
+        digits
+            .chars()
+            .map(|digit| KeyEvent::new(KeyCode::Char(digit), KeyModifiers::NONE))
+            .chain(self.keys.iter().copied())
+            .collect()
+    }
+}
+
+/// Collects the keys of the command being typed, and keeps the last command
+/// that changed the buffer. Commands typed on the command line are never
+/// kept.
+#[derive(Default)]
+pub struct Recorder {
+    keys: Vec<KeyEvent>,
+    count: Option<usize>,
+    digits: Option<usize>,
+    changed: bool,
+    command_line: bool,
+    last: Option<Change>,
+}
+
+impl Recorder {
+    pub fn record(&mut self, event: KeyEvent) {
+        if let Some(digits) = self.digits.take() {
+            self.count = Some(self.count.unwrap_or(1).saturating_mul(digits));
+        }
+        self.keys.push(event);
+    }
+
+    pub fn record_count_digit(&mut self, digit: usize) {
+        self.digits = Some(self.digits.unwrap_or(0).saturating_mul(10) + digit);
+    }
+
+    pub fn mark_changed(&mut self) {
+        self.changed = true;
+    }
+
+    pub fn mark_command_line(&mut self) {
+        self.command_line = true;
+    }
+
+    /// Ends the command, keeping it if it changed the buffer.
+    pub fn finish(&mut self) {
+        if self.changed && !self.command_line {
+            self.last = Some(Change {
+                keys: std::mem::take(&mut self.keys),
+                count: self.count,
+            });
+        }
+        self.discard();
+    }
+
+    pub fn discard(&mut self) {
+        self.keys.clear();
+        self.count = None;
+        self.digits = None;
+        self.changed = false;
+        self.command_line = false;
+    }
+
+    pub fn last(&self) -> Option<&Change> {
+        self.last.as_ref()
+    }
+
+    /// Makes later repeats use `count`, as a count given to `.` does.
+    pub fn set_count(&mut self, count: usize) {
+        if let Some(change) = &mut self.last {
+            change.count = Some(count);
+        }
+    }
+}
@@ -1,67 +1,299 @@
-use std::io::{stdout, Write};
+use std::ops::Range;
 
-use crossterm::cursor::{MoveTo, RestorePosition, SavePosition};
-use crossterm::queue;
+use crossterm::style::{Attribute, Color};
+use unicode_segmentation::UnicodeSegmentation;
 
 use super::buffer::Buffer;
+use super::buffer_list::BufferList;
+use super::frame::{Frame, Style};
+use super::gutter::{self, LineNumbers};
+use super::highlight::{Span, Theme};
+use super::operator;
 use super::terminal::Terminal;
-use super::IOResult;
+use super::window_list::{Orientation, Rect, WindowList};
+use super::{display, IOResult};
 
-pub fn update_line(buffer: &Buffer, terminal: &Terminal, line_number: usize) -> IOResult {
-    let (term_x, _term_y) = terminal.virtual_position();
-    let (width, _height) = Terminal::size()?;
-    queue!(stdout(), SavePosition,)?;
-    Terminal::cursor_to_beginning_of_line()?;
-    Terminal::clear_line_with_cursor()?;
-    let default = String::new();
-    if (line_number as usize) < buffer.len() {
-        let line = buffer.get_line(line_number).unwrap_or(&default);
-        let text = &line[term_x..usize::min(term_x + width as usize, line.len())];
-        print!("{text}");
+pub fn text_area(terminal: &Terminal) -> Result<Rect, std::io::Error> {
+    let (width, height) = terminal.text_area_size()?;
+    Ok(Rect {
+        x: 0,
+        y: 0,
+        width,
+        height,
+    })
+}
+
+pub fn render(
+    buffers: &BufferList,
+    windows: &WindowList,
+    terminal: &mut Terminal,
+    mode: &str,
+    selection: Option<operator::Range>,
+    command_line: &str,
+    command_cursor: Option<usize>,
+) -> IOResult {
+    let (width, height) = terminal.size()?;
+    let area = text_area(terminal)?;
+    let mut frame = Frame::new(width, height);
+    for (id, rect) in windows.rects(area) {
+        let Some(index) = buffers.index_of_number(windows.buffer(id)) else {
+            continue;
+        };
+        let (cursor, position, selection) = if id == windows.active() {
+            (
+                terminal.virtual_cursor(),
+                terminal.virtual_position(),
+                selection,
+            )
+        } else {
+            let (cursor, position) = windows.view(id);
+            (cursor, position, None)
+        };
+        draw_window(
+            &mut frame,
+            buffers.get(index),
+            rect,
+            terminal,
+            cursor.1,
+            position,
+            selection,
+        );
+    }
+    for (orientation, rect) in windows.separators(area) {
+        let symbol = match orientation {
+            Orientation::Horizontal => "─",
+            Orientation::Vertical => "│",
+        };
+        for y in rect.y..rect.y + rect.height {
+            for x in rect.x..rect.x + rect.width {
+                frame.print(x, y, symbol, Style::default());
+            }
+        }
+    }
+    draw_status_line(
+        &mut frame,
+        buffers.current(),
+        terminal,
+        mode,
+        height.saturating_sub(2),
+    );
+    let message_lines: Vec<&str> = command_line.split('\n').collect();
+    let top = height.saturating_sub(message_lines.len());
+    for (row, line) in message_lines.iter().enumerate() {
+        frame.clear_row(top + row, 0);
+        frame.print(0, top + row, line, Style::default());
+    }
+    if let Some(x) = command_cursor {
+        frame.set_cursor(x, height - 1);
     } else {
-        print!("~");
+        let rect = windows.active_rect(area);
+        let (x, y) = terminal.screen_cursor(buffers.current())?;
+        frame.set_cursor(rect.x + x, rect.y + y);
     }
-    queue!(stdout(), RestorePosition)?;
-    stdout().flush()?;
-    Ok(())
+    terminal.draw(frame)
 }
 
-pub fn update_current_line(buffer: &Buffer, terminal: &Terminal) -> IOResult {
-    update_line(buffer, terminal, Terminal::cursor_position()?.1)
+/// A screen row of a window: the buffer line it shows and the grapheme
+/// columns of that line that fit on it.
+struct Row {
+    line: usize,
+    columns: Range<usize>,
 }
 
-pub fn update_line_until_eof(buffer: &Buffer, terminal: &Terminal, line_number: usize) -> IOResult {
-    let (term_x, term_y) = terminal.virtual_position();
-    let (width, height) = Terminal::size()?;
-    if line_number >= term_y && line_number <= term_y + height as usize {
-        queue!(stdout(), SavePosition)?;
-        Terminal::cursor_to(0, line_number - term_y)?;
-        Terminal::clear_from_cursor_down()?;
-        let default = String::from("~");
-        for line_number in line_number..term_y + height {
-            let line = buffer.get_line(line_number).unwrap_or(&default);
-            let mut text = "";
-            if term_x <= line.len() {
-                text = &line[term_x..usize::min(term_x + width as usize, line.len())];
+fn layout_rows(
+    buffer: &Buffer,
+    top: usize,
+    rect: Rect,
+    wrap: Option<bool>,
+    tab_stop: usize,
+) -> Vec<Row> {
+    let mut rows = Vec::new();
+    let mut line = top;
+    while rows.len() < rect.height && line < buffer.len() {
+        match wrap {
+            Some(at_words) => {
+                let text = buffer.get_line(line).unwrap_or_default();
+                let starts = display::wrap(&text, rect.width, at_words, tab_stop);
+                for (index, start) in starts.iter().enumerate() {
+                    let end = starts.get(index + 1).copied().unwrap_or(usize::MAX);
+                    rows.push(Row {
+                        line,
+                        columns: *start..end,
+                    });
+                }
             }
-            if line_number == term_y + height - 1 {
-                print!("{text}");
+            None => rows.push(Row {
+                line,
+                columns: 0..usize::MAX,
+            }),
+        }
+        line += 1;
+    }
+    rows.truncate(rect.height);
+    rows
+}
+
+fn draw_window(
+    frame: &mut Frame,
+    buffer: &Buffer,
+    rect: Rect,
+    terminal: &Terminal,
+    cursor_row: usize,
+    (term_x, term_y): (usize, usize),
+    selection: Option<operator::Range>,
+) {
+    let line_numbers = terminal.line_numbers();
+    let gutter_width = usize::min(gutter::width(line_numbers, buffer.len()), rect.width);
+    let rect = Rect {
+        x: rect.x + gutter_width,
+        width: rect.width - gutter_width,
+        ..rect
+    };
+    if rect.width == 0 {
+        return;
+    }
+    let tab_stop = terminal.options().tab_stop;
+    let rows = layout_rows(buffer, term_y, rect, terminal.wrap(), tab_stop);
+    for (y, row) in (rect.y..).zip(&rows) {
+        if row.columns.start == 0 {
+            draw_line_number(
+                frame,
+                line_numbers,
+                (rect.x - gutter_width, y),
+                gutter_width,
+                row.line,
+                cursor_row,
+            );
+        }
+        let line = buffer.get_line(row.line).unwrap_or_default();
+        let spans = buffer.highlight_line(row.line);
+        let selected = selection
+            .and_then(|range| range.columns(row.line, buffer.line_len(row.line)))
+            .unwrap_or_default();
+        let style = |column, index| {
+            let style = style_at(&spans, index, terminal.options().theme);
+            if selected.contains(&column) {
+                Style {
+                    attributes: style.attributes | Attribute::Reverse,
+                    ..style
+                }
             } else {
-                println!("{text}");
+                style
+            }
+        };
+        let mut x = rect.x;
+        if terminal.wrap().is_some() {
+            let graphemes = display::expand_tabs(&line, tab_stop).enumerate();
+            for (column, (index, grapheme)) in
+                graphemes.skip(row.columns.start).take(row.columns.len())
+            {
+                x = frame.print(x, y, &grapheme, style(column, index));
+            }
+        } else {
+            let pieces = display::clip(&line, term_x, rect.width, tab_stop);
+            let first = pieces
+                .first()
+                .map_or(0, |(index, _)| line[..*index].graphemes(true).count());
+            for (column, (index, text)) in (first..).zip(pieces) {
+                x = frame.print(x, y, &text, style(column, index));
             }
-            Terminal::cursor_to_beginning_of_line()?;
         }
-        queue!(stdout(), RestorePosition)?;
-        stdout().flush()?;
+        if line.is_empty() && selected.contains(&0) && term_x == 0 {
+            frame.print(rect.x, y, " ", style(0, 0));
+        }
+    }
+    for y in rect.y + rows.len()..rect.y + rect.height {
+        frame.print(rect.x, y, &terminal.options().filler, Style::default());
+    }
+}
+
+fn draw_line_number(
+    frame: &mut Frame,
+    line_numbers: LineNumbers,
+    (x, y): (usize, usize),
+    width: usize,
+    row: usize,
+    cursor_row: usize,
+) {
+    if width == 0 {
+        return;
+    }
+    let style = if row == cursor_row {
+        Style::default()
+    } else {
+        Style {
+            foreground: Some(Color::DarkGrey),
+            ..Style::default()
+        }
+    };
+    frame.print(
+        x,
+        y,
+        &gutter::label(line_numbers, width, row, cursor_row),
+        style,
+    );
+}
+
+fn draw_status_line(
+    frame: &mut Frame,
+    buffer: &Buffer,
+    terminal: &Terminal,
+    mode: &str,
+    row: usize,
+) {
+    let style = Style {
+        attributes: Attribute::Reverse.into(),
+        ..Style::default()
+    };
+    let (width, _height) = frame.size();
+    let (x, y) = terminal.virtual_cursor();
+    let modified = if buffer.is_modified() { " [+]" } else { "" };
+    let lines = buffer.len();
+    let right = format!(
+        "{}:{}  {lines}L  {}% ",
+        y + 1,
+        x + 1,
+        (y + 1) * 100 / usize::max(lines, 1)
+    );
+    let right_width = display::width(&right);
+    let mode = format!(" {mode}  ");
+    let name_width = width
+        .saturating_sub(right_width + 1)
+        .saturating_sub(display::width(&mode) + display::width(modified));
+    let left = format!(
+        "{mode}{}{modified}",
+        shorten_start(buffer.name(), name_width)
+    );
+    frame.print(0, row, &" ".repeat(width), style);
+    frame.print(0, row, &left, style);
+    frame.print(width.saturating_sub(right_width), row, &right, style);
+}
+
+/// Fits `name` into `width` cells by cutting off its start, which is marked
+/// with `<`.
+fn shorten_start(name: &str, width: usize) -> String {
+    if display::width(name) <= width {
+        return String::from(name);
+    }
+    if width == 0 {
+        return String::new();
+    }
+    let mut used = 1;
+    let mut kept = Vec::new();
+    for grapheme in name.graphemes(true).rev() {
+        used += display::width(grapheme);
+        if used > width {
+            break;
+        }
+        kept.push(grapheme);
     }
-    Ok(())
+    kept.reverse();
+    format!("<{}", kept.concat())
 }
 
-pub fn update_command_text(command_text: &String) -> IOResult {
-    let (_, eof) = Terminal::size()?;
-    queue!(stdout(), MoveTo(0, eof as u16))?;
-    Terminal::clear_line_with_cursor()?;
-    print!("{command_text}");
-    stdout().flush()?;
-    Ok(())
+fn style_at(spans: &[Span], index: usize, theme: Theme) -> Style {
+    spans
+        .iter()
+        .find(|span| span.start <= index && index < span.end)
+        .map_or_else(Style::default, |span| theme.style(span.highlight))
 }
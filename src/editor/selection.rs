@@ -0,0 +1,60 @@
+use super::buffer::Buffer;
+use super::operator::Range;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Shape {
+    Characters,
+    Lines,
+    Block,
+}
+
+/// A Visual mode selection, which runs from where it was started to the
+/// cursor. `to_line_end` makes a blockwise selection reach the end of every
+/// line, as after `$`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Selection {
+    pub shape: Shape,
+    pub anchor: (usize, usize),
+    pub to_line_end: bool,
+}
+
+impl Selection {
+    pub fn new(shape: Shape, anchor: (usize, usize)) -> Self {
+        Selection {
+            shape,
+            anchor,
+            to_line_end: false,
+        }
+    }
+
+    /// The text selected with the cursor at `cursor`. A characterwise
+    /// selection that ends on an empty line takes its line break too.
+    pub fn range(self, buffer: &Buffer, cursor: (usize, usize)) -> Range {
+        let (start, end) = if (cursor.1, cursor.0) < (self.anchor.1, self.anchor.0) {
+            (cursor, self.anchor)
+        } else {
+            (self.anchor, cursor)
+        };
+        match self.shape {
+            Shape::Characters => {
+                let line_len = buffer.line_len(end.1);
+                let end = if end.0 >= line_len && end.1 + 1 < buffer.len() {
+                    (0, end.1 + 1)
+                } else {
+                    (usize::min(end.0 + 1, line_len), end.1)
+                };
+                Range::Characters(start, end)
+            }
+            Shape::Lines => Range::Lines(start.1, end.1),
+            Shape::Block => {
+                let left = usize::min(self.anchor.0, cursor.0);
+                let right = if self.to_line_end {
+                    usize::MAX
+                } else {
+                    usize::max(self.anchor.0, cursor.0) + 1
+                };
+                Range::Block((left, start.1), (right, end.1))
+            }
+        }
+    }
+}
@@ -1,26 +1,29 @@
-use crossterm::cursor::{
-    position, MoveDown, MoveLeft, MoveRight, MoveTo, MoveToColumn, MoveUp, SetCursorStyle,
-};
-use crossterm::terminal::{
-    disable_raw_mode, enable_raw_mode, size, Clear, ClearType, DisableLineWrap, EnableLineWrap,
-};
-use crossterm::{execute, queue};
-use std::io::{stdout, Write};
+use std::time::Duration;
 
+use crossterm::cursor::SetCursorStyle;
+use crossterm::event::Event;
+use crossterm::terminal::ClearType;
+
+use super::backend::{Backend, CrosstermBackend};
 use super::buffer::Buffer;
-use super::{screen, IOResult};
+use super::frame::{Frame, Style};
+use super::gutter::{self, LineNumbers};
+use super::motion::Viewport;
+use super::options::{Options, Value};
+use super::{display, IOResult};
 
 pub struct Terminal {
+    backend: Box<dyn Backend>,
+    frame: Option<Frame>,
     virtual_cursor: (usize, usize),
     virtual_position: (usize, usize),
+    view_size: Option<(usize, usize)>,
+    options: Options,
 }
 
 impl Default for Terminal {
     fn default() -> Self {
-        Terminal {
-            virtual_cursor: (0, 0),
-            virtual_position: (0, 0),
-        }
+        Terminal::with_backend(Box::new(CrosstermBackend::new()))
     }
 }
 
@@ -28,16 +31,20 @@ impl Terminal {
     pub fn new() -> Self {
         Terminal::default()
     }
-    pub fn init() -> IOResult {
-        enable_raw_mode()?;
-        queue!(
-            stdout(),
-            Clear(ClearType::All),
-            DisableLineWrap,
-            SetCursorStyle::BlinkingBlock
-        )?;
-        stdout().flush()?;
-        Ok(())
+
+    pub fn with_backend(backend: Box<dyn Backend>) -> Self {
+        Terminal {
+            backend,
+            frame: None,
+            virtual_cursor: (0, 0),
+            virtual_position: (0, 0),
+            view_size: None,
+            options: Options::default(),
+        }
+    }
+
+    pub fn init(&mut self) -> IOResult {
+        self.backend.init()
     }
 
     pub fn virtual_cursor(&self) -> (usize, usize) {
@@ -48,186 +55,331 @@ impl Terminal {
         self.virtual_position
     }
 
-    pub fn cursor_position() -> Result<(usize, usize), std::io::Error> {
-        let (x, y) = position()?;
-        Ok((x as usize, y as usize))
+    pub fn set_virtual_position(&mut self, position: (usize, usize)) {
+        self.virtual_position = position;
     }
 
-    pub fn clean_up() -> IOResult {
-        execute!(stdout(), EnableLineWrap)?;
-        disable_raw_mode()?;
-        Ok(())
+    pub fn options(&self) -> &Options {
+        &self.options
     }
 
-    pub fn size() -> Result<(usize, usize), std::io::Error> {
-        let (x, y) = size()?;
-        Ok((x as usize, y as usize))
+    /// Applies one `:set` argument, returning the text to show for a query.
+    pub fn apply_option(&mut self, argument: &str) -> Result<Option<String>, String> {
+        let result = self.options.apply(argument);
+        self.options_changed();
+        result
     }
 
-    pub fn clear_from_cursor_down() -> IOResult {
-        execute!(stdout(), Clear(ClearType::FromCursorDown))?;
-        Ok(())
+    pub fn set_option(&mut self, name: &str, value: Value) -> Result<(), String> {
+        let result = self.options.set(name, value);
+        self.options_changed();
+        result
     }
 
-    pub fn clear_line_with_cursor() -> IOResult {
-        execute!(stdout(), Clear(ClearType::CurrentLine))?;
-        Ok(())
+    fn options_changed(&mut self) {
+        if self.options.wrap {
+            self.virtual_position.0 = 0;
+        }
     }
 
-    pub fn goto_beginning_of_line(&mut self, buffer: &Buffer) -> IOResult {
-        self.goto(buffer, 0, self.virtual_cursor.1)?;
-        Ok(())
+    pub fn line_numbers(&self) -> LineNumbers {
+        self.options.line_numbers()
     }
 
-    pub fn cursor_to(x: usize, y: usize) -> IOResult {
-        execute!(stdout(), MoveTo(x as u16, y as u16))?;
-        Ok(())
+    pub fn gutter_width(&self, buffer: &Buffer) -> usize {
+        gutter::width(self.line_numbers(), buffer.len())
     }
 
-    pub fn cursor_to_beginning_of_line() -> IOResult {
-        execute!(stdout(), MoveToColumn(0))?;
-        Ok(())
+    /// Whether long lines are soft-wrapped, and if so whether they break at
+    /// word boundaries.
+    pub fn wrap(&self) -> Option<bool> {
+        self.options.wrap.then_some(self.options.linebreak)
     }
 
-    pub fn goto(&mut self, buffer: &Buffer, x: usize, y: usize) -> IOResult {
-        let (width, height) = Self::size()?;
-        let new_cursor_x;
-        let new_cursor_y;
-        self.virtual_cursor.0 = x;
-        let mut rerender = false;
-        if x < self.virtual_position.0 {
-            self.virtual_position.0 = x;
-            new_cursor_x = 0;
-            rerender = true;
-        } else if x > self.virtual_position.0 + width {
-            let diff = x - self.virtual_position.0 - width;
-            self.virtual_position.0 += diff;
-            new_cursor_x = width;
-            rerender = true;
-        } else {
-            let diff = x - self.virtual_position.0;
-            new_cursor_x = diff
-        }
-
-        if y < self.virtual_position.1 {
-            self.virtual_position.1 = y;
-            new_cursor_y = 0;
-            rerender = true;
-        } else if y > self.virtual_position.1 + height {
-            let diff = y - self.virtual_position.1 - height;
-            self.virtual_position.1 += diff;
-            new_cursor_y = height;
-            rerender = true;
-        } else {
-            let diff = y - self.virtual_position.1;
-            new_cursor_y = diff
+    fn text_width(&self, buffer: &Buffer) -> Result<usize, std::io::Error> {
+        let (width, _height) = self.view_size()?;
+        Ok(usize::max(
+            width.saturating_sub(self.gutter_width(buffer)),
+            1,
+        ))
+    }
+
+    fn wrap_starts(&self, buffer: &Buffer, y: usize, width: usize) -> Vec<usize> {
+        let line = buffer.get_line(y).unwrap_or_default();
+        display::wrap(&line, width, self.options.linebreak, self.options.tab_stop)
+    }
+
+    /// The screen row within line `y` that column `x` is on, and its cell
+    /// offset from the start of that row.
+    fn wrapped_position(
+        &self,
+        buffer: &Buffer,
+        (x, y): (usize, usize),
+        width: usize,
+    ) -> (usize, usize) {
+        let line = buffer.get_line(y).unwrap_or_default();
+        let starts = display::wrap(&line, width, self.options.linebreak, self.options.tab_stop);
+        let row = starts.iter().rposition(|start| *start <= x).unwrap_or(0);
+        let tab_stop = self.options.tab_stop;
+        let cell = display::width_until(&line, x, tab_stop)
+            - display::width_until(&line, starts[row], tab_stop);
+        (row, usize::min(cell, width - 1))
+    }
+
+    pub fn viewport(&self, buffer: &Buffer) -> Result<Viewport, std::io::Error> {
+        let (_width, height) = self.view_size()?;
+        let top = self.virtual_position.1;
+        let available = buffer.len().saturating_sub(top);
+        if !self.options.wrap {
+            return Ok(Viewport {
+                top,
+                lines: usize::min(height, available),
+            });
         }
-        if rerender {
-            screen::update_line_until_eof(buffer, self, self.virtual_position.1)?;
+        let width = self.text_width(buffer)?;
+        let mut used = 0;
+        let lines = (top..buffer.len())
+            .take_while(|&y| {
+                let fits = used < height;
+                used += self.wrap_starts(buffer, y, width).len();
+                fits
+            })
+            .count();
+        Ok(Viewport { top, lines })
+    }
+
+    pub fn screen_cursor(&self, buffer: &Buffer) -> Result<(usize, usize), std::io::Error> {
+        let (x, y) = self.virtual_cursor;
+        let gutter_width = self.gutter_width(buffer);
+        if self.options.wrap {
+            let width = self.text_width(buffer)?;
+            let (row, cell) = self.wrapped_position(buffer, (x, y), width);
+            let rows_above: usize = (self.virtual_position.1..y)
+                .map(|y| self.wrap_starts(buffer, y, width).len())
+                .sum();
+            return Ok((gutter_width + cell, rows_above + row));
         }
-        Self::cursor_to(new_cursor_x, new_cursor_y)?;
-        Ok(())
+        let line = buffer.get_line(y).unwrap_or_default();
+        Ok((
+            gutter_width
+                + display::width_until(&line, x, self.options.tab_stop)
+                    .saturating_sub(self.virtual_position.0),
+            y.saturating_sub(self.virtual_position.1),
+        ))
     }
 
-    pub fn move_down(&mut self, buffer: &Buffer) -> IOResult {
-        if self.virtual_cursor.1 >= buffer.len() - 1 {
-            return Ok(());
+    pub fn clean_up(&mut self) -> IOResult {
+        self.backend.clean_up()
+    }
+
+    pub fn size(&self) -> Result<(usize, usize), std::io::Error> {
+        self.backend.size()
+    }
+
+    pub fn text_area_size(&self) -> Result<(usize, usize), std::io::Error> {
+        let (width, height) = self.size()?;
+        Ok((width, usize::max(height.saturating_sub(2), 1)))
+    }
+
+    pub fn set_view_size(&mut self, size: (usize, usize)) {
+        self.view_size = Some(size);
+    }
+
+    fn view_size(&self) -> Result<(usize, usize), std::io::Error> {
+        match self.view_size {
+            Some((width, height)) => Ok((usize::max(width, 1), usize::max(height, 1))),
+            None => self.text_area_size(),
         }
-        self.virtual_cursor.1 += 1;
-        let default = String::new();
-        let (_cursor_x, cursor_y) = Terminal::cursor_position()?;
-        let (_width, height) = Terminal::size()?;
-        let mut rerender = false;
-        if cursor_y == height - 1 {
-            self.virtual_position.1 += 1;
-            rerender = true;
-        } else {
-            execute!(stdout(), MoveDown(1))?;
+    }
+
+    pub fn read_event(&mut self) -> Result<Option<Event>, std::io::Error> {
+        self.backend.read_event()
+    }
+
+    pub fn poll_event(&mut self, timeout: Duration) -> Result<bool, std::io::Error> {
+        self.backend.poll_event(timeout)
+    }
+
+    pub fn set_clipboard(&mut self, text: &str) -> IOResult {
+        self.backend.set_clipboard(text)
+    }
+
+    pub fn set_cursor_style(&mut self, style: SetCursorStyle) -> IOResult {
+        self.backend.set_cursor_style(style)
+    }
+
+    pub fn draw(&mut self, frame: Frame) -> IOResult {
+        self.backend.hide_cursor()?;
+        self.backend.set_style(Style::default())?;
+        let changes = match &self.frame {
+            Some(previous) if previous.size() == frame.size() => frame.diff(previous),
+            _ => {
+                self.backend.clear(ClearType::All)?;
+                let (width, height) = frame.size();
+                frame.diff(&Frame::new(width, height))
+            }
+        };
+        for (x, y, style, text) in changes {
+            self.backend.move_cursor_to(x, y)?;
+            self.backend.set_style(style)?;
+            self.backend.write(&text)?;
         }
-        let line = buffer.get_line(self.virtual_cursor.1).unwrap_or(&default);
-        if line.len() < self.virtual_cursor.0 + 1 {
-            self.goto(buffer, usize::max(line.len(), 1) - 1, self.virtual_cursor.1)?;
+        self.backend.set_style(Style::default())?;
+        let (x, y) = frame.cursor();
+        self.backend.move_cursor_to(x, y)?;
+        self.backend.show_cursor()?;
+        self.backend.flush()?;
+        self.frame = Some(frame);
+        Ok(())
+    }
+
+    pub fn goto_beginning_of_line(&mut self, buffer: &Buffer) -> IOResult {
+        self.goto(buffer, 0, self.virtual_cursor.1)?;
+        Ok(())
+    }
+
+    pub fn goto(&mut self, buffer: &Buffer, x: usize, y: usize) -> IOResult {
+        self.virtual_cursor = (x, y);
+        if self.options.wrap {
+            return self.scroll_wrapped(buffer);
+        }
+        let (_width, height) = self.view_size()?;
+        let width = self.text_width(buffer)?;
+        let line = buffer.get_line(y).unwrap_or_default();
+        let cell_x = display::width_until(&line, x, self.options.tab_stop);
+        let cell_width = display::width_at(&line, x, self.options.tab_stop);
+        if cell_x < self.virtual_position.0 {
+            self.virtual_position.0 = cell_x;
+        } else if cell_x + cell_width > self.virtual_position.0 + width {
+            self.virtual_position.0 = cell_x + cell_width - width;
         }
 
-        if rerender {
-            screen::update_line_until_eof(buffer, &self, self.virtual_position.1)?;
+        let scroll_off = usize::min(self.options.scroll_off, (height - 1) / 2);
+        if y < self.virtual_position.1 + scroll_off {
+            self.virtual_position.1 = y.saturating_sub(scroll_off);
+        } else if y + scroll_off >= self.virtual_position.1 + height {
+            let last_top = buffer.len().saturating_sub(height);
+            self.virtual_position.1 = usize::max(
+                usize::min(y + scroll_off + 1 - height, last_top),
+                (y + 1).saturating_sub(height),
+            );
         }
         Ok(())
     }
 
-    pub fn move_up(&mut self, buffer: &Buffer) -> IOResult {
-        if self.virtual_cursor.1 == 0 {
+    /// Scrolls so the cursor's screen row is visible, counting every screen
+    /// row of the wrapped lines above it. `scrolloff` keeps that many lines
+    /// visible around the cursor line.
+    fn scroll_wrapped(&mut self, buffer: &Buffer) -> IOResult {
+        let (_width, height) = self.view_size()?;
+        let width = self.text_width(buffer)?;
+        let (_x, y) = self.virtual_cursor;
+        let scroll_off = usize::min(self.options.scroll_off, (height - 1) / 2);
+        self.virtual_position.0 = 0;
+        if y <= self.virtual_position.1 + scroll_off {
+            self.virtual_position.1 =
+                usize::min(y.saturating_sub(scroll_off), self.virtual_position.1);
             return Ok(());
         }
-        self.virtual_cursor.1 -= 1;
-        let default = String::new();
-        let (_cursor_x, cursor_y) = Terminal::cursor_position()?;
-        let mut rerender = false;
-        if cursor_y == 0 {
-            self.virtual_position.1 -= 1;
-            rerender = true;
-        } else {
-            execute!(stdout(), MoveUp(1))?;
-        }
-        let line = buffer.get_line(self.virtual_cursor.1).unwrap_or(&default);
-        if line.len() < self.virtual_cursor.0 + 1 {
-            self.goto(buffer, usize::max(line.len(), 1) - 1, self.virtual_cursor.1)?;
-        } else if rerender {
-            screen::update_line_until_eof(buffer, &self, self.virtual_position.1)?;
+        let (row, _cell) = self.wrapped_position(buffer, self.virtual_cursor, width);
+        let below = usize::min(y + scroll_off, buffer.len().saturating_sub(1));
+        let mut top = y;
+        let mut used = row
+            + 1
+            + (y + 1..=below)
+                .map(|y| self.wrap_starts(buffer, y, width).len())
+                .sum::<usize>();
+        while top > self.virtual_position.1 {
+            let rows = self.wrap_starts(buffer, top - 1, width).len();
+            if used + rows > height {
+                break;
+            }
+            used += rows;
+            top -= 1;
         }
+        self.virtual_position.1 = top;
         Ok(())
     }
 
-    pub fn move_right(&mut self, buffer: &Buffer) -> IOResult {
-        self.move_right_base(buffer, false)
+    /// Moves to the screen row below or above the cursor, which is the next
+    /// or previous line unless wrapping splits the line into several rows.
+    pub fn move_screen_row(&mut self, buffer: &Buffer, down: bool) -> IOResult {
+        if !self.options.wrap {
+            return if down {
+                self.move_down(buffer)
+            } else {
+                self.move_up(buffer)
+            };
+        }
+        let width = self.text_width(buffer)?;
+        let (x, y) = self.virtual_cursor;
+        let (row, cell) = self.wrapped_position(buffer, (x, y), width);
+        let rows = self.wrap_starts(buffer, y, width).len();
+        let (y, row) = if down && row + 1 < rows {
+            (y, row + 1)
+        } else if down && y + 1 < buffer.len() {
+            (y + 1, 0)
+        } else if !down && row > 0 {
+            (y, row - 1)
+        } else if !down && y > 0 {
+            (y - 1, self.wrap_starts(buffer, y - 1, width).len() - 1)
+        } else {
+            return Ok(());
+        };
+        let line = buffer.get_line(y).unwrap_or_default();
+        let starts = display::wrap(&line, width, self.options.linebreak, self.options.tab_stop);
+        let start = starts[row];
+        let end = starts.get(row + 1).copied().unwrap_or(buffer.line_len(y));
+        let mut x = start;
+        let mut offset = 0;
+        for (_index, grapheme) in display::expand_tabs(&line, self.options.tab_stop)
+            .skip(start)
+            .take(end - start)
+        {
+            offset += display::width(&grapheme);
+            if offset > cell {
+                break;
+            }
+            x += 1;
+        }
+        self.goto(
+            buffer,
+            usize::max(usize::min(x, end.saturating_sub(1)), start),
+            y,
+        )
     }
 
-    pub fn move_right_for_insert(&mut self, buffer: &Buffer) -> IOResult {
-        self.move_right_base(buffer, true)
+    pub fn move_down(&mut self, buffer: &Buffer) -> IOResult {
+        if self.virtual_cursor.1 + 1 >= buffer.len() {
+            return Ok(());
+        }
+        let y = self.virtual_cursor.1 + 1;
+        let line_len = buffer.line_len(y);
+        let x = usize::min(self.virtual_cursor.0, usize::max(line_len, 1) - 1);
+        self.goto(buffer, x, y)
     }
 
-    fn move_right_base(&mut self, buffer: &Buffer, for_insert_mode: bool) -> IOResult {
-        let default = String::new();
-        let line = buffer.get_line(self.virtual_cursor.1).unwrap_or(&default);
-        let mut line_len = usize::max(line.len(), 0);
-        if !for_insert_mode {
-            line_len = usize::max(line.len(), 1) - 1;
-        }
-        if self.virtual_cursor.0 >= line_len {
+    pub fn move_up(&mut self, buffer: &Buffer) -> IOResult {
+        if self.virtual_cursor.1 == 0 {
             return Ok(());
         }
-        self.virtual_cursor.0 += 1;
-        let (cursor_x, _cursor_y) = Terminal::cursor_position()?;
-        let (width, _height) = Terminal::size()?;
-        let mut rerender = false;
-        if cursor_x == width - 1 {
-            self.virtual_position.0 += 1;
-            rerender = true;
-        } else {
-            execute!(stdout(), MoveRight(1))?;
-        }
-        if rerender {
-            screen::update_line_until_eof(buffer, &self, self.virtual_position.1)?;
+        let y = self.virtual_cursor.1 - 1;
+        let line_len = buffer.line_len(y);
+        let x = usize::min(self.virtual_cursor.0, usize::max(line_len, 1) - 1);
+        self.goto(buffer, x, y)
+    }
+
+    pub fn move_right_for_insert(&mut self, buffer: &Buffer) -> IOResult {
+        if self.virtual_cursor.0 >= buffer.line_len(self.virtual_cursor.1) {
+            return Ok(());
         }
-        Ok(())
+        self.goto(buffer, self.virtual_cursor.0 + 1, self.virtual_cursor.1)
     }
 
     pub fn move_left(&mut self, buffer: &Buffer) -> IOResult {
-        if self.virtual_cursor.0 <= 0 {
+        if self.virtual_cursor.0 == 0 {
             return Ok(());
         }
-        self.virtual_cursor.0 -= 1;
-        let (cursor_x, _cursor_y) = Terminal::cursor_position()?;
-        let mut rerender = false;
-        if cursor_x == 0 {
-            self.virtual_position.0 -= 1;
-            rerender = true;
-        } else {
-            execute!(stdout(), MoveLeft(1))?;
-        }
-        if rerender {
-            screen::update_line_until_eof(buffer, &self, self.virtual_position.1)?;
-        }
-        Ok(())
+        self.goto(buffer, self.virtual_cursor.0 - 1, self.virtual_cursor.1)
     }
 }
@@ -0,0 +1,330 @@
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    fn contains(&self, x: usize, y: usize) -> bool {
+        (self.x..self.x + self.width).contains(&x) && (self.y..self.y + self.height).contains(&y)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Left,
+    Down,
+    Up,
+    Right,
+}
+
+/// How the children of a split are laid out: `Horizontal` stacks them on
+/// top of each other, `Vertical` places them side by side.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Orientation {
+    Horizontal,
+    Vertical,
+}
+
+struct Window {
+    id: usize,
+    buffer: usize,
+    cursor: (usize, usize),
+    position: (usize, usize),
+}
+
+enum Layout {
+    Window(usize),
+    Split(Orientation, Vec<Layout>),
+}
+
+/// The windows on screen, each showing a buffer by its number. Like
+/// `BufferList`, the focused window's cursor and scroll live in `Terminal`
+/// and are only saved here when focus moves away.
+pub struct WindowList {
+    windows: Vec<Window>,
+    layout: Layout,
+    active: usize,
+    next_id: usize,
+}
+
+impl WindowList {
+    pub fn new(buffer: usize) -> Self {
+        WindowList {
+            windows: vec![Window {
+                id: 0,
+                buffer,
+                cursor: (0, 0),
+                position: (0, 0),
+            }],
+            layout: Layout::Window(0),
+            active: 0,
+            next_id: 1,
+        }
+    }
+
+    fn window(&self, id: usize) -> &Window {
+        self.windows
+            .iter()
+            .find(|window| window.id == id)
+            .expect("window ids in the layout always exist")
+    }
+
+    fn window_mut(&mut self, id: usize) -> &mut Window {
+        self.windows
+            .iter_mut()
+            .find(|window| window.id == id)
+            .expect("window ids in the layout always exist")
+    }
+
+    pub fn len(&self) -> usize {
+        self.windows.len()
+    }
+
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    pub fn buffer(&self, id: usize) -> usize {
+        self.window(id).buffer
+    }
+
+    pub fn set_buffer(&mut self, buffer: usize) {
+        self.window_mut(self.active).buffer = buffer;
+    }
+
+    /// Points every window showing buffer `old` at buffer `new` instead.
+    pub fn replace_buffer(&mut self, old: usize, new: usize) {
+        for window in &mut self.windows {
+            if window.buffer == old {
+                window.buffer = new;
+                window.cursor = (0, 0);
+                window.position = (0, 0);
+            }
+        }
+    }
+
+    pub fn save_view(&mut self, cursor: (usize, usize), position: (usize, usize)) {
+        let window = self.window_mut(self.active);
+        window.cursor = cursor;
+        window.position = position;
+    }
+
+    pub fn view(&self, id: usize) -> ((usize, usize), (usize, usize)) {
+        let window = self.window(id);
+        (window.cursor, window.position)
+    }
+
+    pub fn select(&mut self, id: usize) {
+        self.active = id;
+    }
+
+    /// Splits the active window, giving the new window the active window's
+    /// buffer and view and placing it above or to the left.
+    pub fn split(&mut self, orientation: Orientation) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let (cursor, position) = self.view(self.active);
+        self.windows.push(Window {
+            id,
+            buffer: self.buffer(self.active),
+            cursor,
+            position,
+        });
+        split(&mut self.layout, self.active, id, orientation);
+        id
+    }
+
+    /// Removes the active window and returns the window that should get
+    /// focus, or `None` if it is the last window.
+    pub fn close(&mut self) -> Option<usize> {
+        if self.windows.len() == 1 {
+            return None;
+        }
+        let mut order = Vec::new();
+        leaves(&self.layout, &mut order);
+        let index = order.iter().position(|id| *id == self.active)?;
+        let next = if index + 1 < order.len() {
+            order[index + 1]
+        } else {
+            order[index - 1]
+        };
+        remove(&mut self.layout, self.active);
+        self.windows.retain(|window| window.id != self.active);
+        Some(next)
+    }
+
+    /// The window after the active one, wrapping around to the first.
+    pub fn next(&self) -> usize {
+        let mut order = Vec::new();
+        leaves(&self.layout, &mut order);
+        let index = order.iter().position(|id| *id == self.active).unwrap_or(0);
+        order[(index + 1) % order.len()]
+    }
+
+    pub fn rects(&self, area: Rect) -> Vec<(usize, Rect)> {
+        let mut rects = Vec::new();
+        arrange(&self.layout, area, &mut rects, &mut Vec::new());
+        rects
+    }
+
+    /// The one-cell-wide or one-cell-high strips between windows.
+    pub fn separators(&self, area: Rect) -> Vec<(Orientation, Rect)> {
+        let mut separators = Vec::new();
+        arrange(&self.layout, area, &mut Vec::new(), &mut separators);
+        separators
+    }
+
+    pub fn active_rect(&self, area: Rect) -> Rect {
+        self.rects(area)
+            .into_iter()
+            .find(|(id, _rect)| *id == self.active)
+            .map_or(area, |(_id, rect)| rect)
+    }
+
+    /// Finds the window next to the active one in `direction`, lined up with
+    /// the cursor at `(x, y)` on screen.
+    pub fn neighbour(
+        &self,
+        area: Rect,
+        direction: Direction,
+        (x, y): (usize, usize),
+    ) -> Option<usize> {
+        let rect = self.active_rect(area);
+        let (x, y) = match direction {
+            Direction::Left => (rect.x.checked_sub(2)?, y),
+            Direction::Right => (rect.x + rect.width + 1, y),
+            Direction::Up => (x, rect.y.checked_sub(2)?),
+            Direction::Down => (x, rect.y + rect.height + 1),
+        };
+        self.rects(area)
+            .into_iter()
+            .find(|(_id, rect)| rect.contains(x, y))
+            .map(|(id, _rect)| id)
+    }
+}
+
+fn split(layout: &mut Layout, target: usize, id: usize, orientation: Orientation) -> bool {
+    match layout {
+        Layout::Window(window) if *window == target => {
+            *layout = Layout::Split(
+                orientation,
+                vec![Layout::Window(id), Layout::Window(target)],
+            );
+            true
+        }
+        Layout::Window(_) => false,
+        Layout::Split(split_orientation, children) => {
+            let index = children
+                .iter()
+                .position(|child| matches!(child, Layout::Window(window) if *window == target));
+            match index {
+                Some(index) if *split_orientation == orientation => {
+                    children.insert(index, Layout::Window(id));
+                    true
+                }
+                _ => children
+                    .iter_mut()
+                    .any(|child| split(child, target, id, orientation)),
+            }
+        }
+    }
+}
+
+fn remove(layout: &mut Layout, target: usize) -> bool {
+    let Layout::Split(_, children) = layout else {
+        return false;
+    };
+    let index = children
+        .iter()
+        .position(|child| matches!(child, Layout::Window(window) if *window == target));
+    match index {
+        Some(index) => {
+            children.remove(index);
+        }
+        None => {
+            if !children.iter_mut().any(|child| remove(child, target)) {
+                return false;
+            }
+        }
+    }
+    if children.len() == 1 {
+        let child = children.remove(0);
+        *layout = child;
+    }
+    true
+}
+
+fn leaves(layout: &Layout, ids: &mut Vec<usize>) {
+    match layout {
+        Layout::Window(id) => ids.push(*id),
+        Layout::Split(_, children) => {
+            for child in children {
+                leaves(child, ids);
+            }
+        }
+    }
+}
+
+fn arrange(
+    layout: &Layout,
+    area: Rect,
+    rects: &mut Vec<(usize, Rect)>,
+    separators: &mut Vec<(Orientation, Rect)>,
+) {
+    let (orientation, children) = match layout {
+        Layout::Window(id) => {
+            rects.push((*id, area));
+            return;
+        }
+        Layout::Split(orientation, children) => (*orientation, children),
+    };
+    let count = children.len();
+    let total = match orientation {
+        Orientation::Horizontal => area.height,
+        Orientation::Vertical => area.width,
+    };
+    let available = total.saturating_sub(count - 1);
+    let mut offset = 0;
+    for (index, child) in children.iter().enumerate() {
+        let last = index + 1 == count;
+        let size = if last {
+            available - available / count * (count - 1)
+        } else {
+            available / count
+        };
+        let (child_area, separator) = match orientation {
+            Orientation::Horizontal => (
+                Rect {
+                    y: area.y + offset,
+                    height: size,
+                    ..area
+                },
+                Rect {
+                    y: area.y + offset + size,
+                    height: 1,
+                    ..area
+                },
+            ),
+            Orientation::Vertical => (
+                Rect {
+                    x: area.x + offset,
+                    width: size,
+                    ..area
+                },
+                Rect {
+                    x: area.x + offset + size,
+                    width: 1,
+                    ..area
+                },
+            ),
+        };
+        arrange(child, child_area, rects, separators);
+        if !last {
+            separators.push((orientation, separator));
+        }
+        offset += size + 1;
+    }
+}
@@ -0,0 +1,2 @@
+#![warn(clippy::all, clippy::pedantic)]
+pub mod editor;
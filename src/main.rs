@@ -1,13 +1,7 @@
 #![warn(clippy::all, clippy::pedantic)]
-use editor::Editor;
-
-pub mod editor;
+use text_editor::editor::Editor;
 
 fn main() {
     let args: Vec<String> = std::env::args().collect();
-    let mut path: Option<&String> = None;
-    if args.len() > 1 {
-        path = Some(&args[1]);
-    }
-    Editor::new(path).run();
+    Editor::new(args.get(1).map(String::as_str)).run();
 }
@@ -0,0 +1,160 @@
+#![allow(dead_code)]
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
+use text_editor::editor::{Editor, MemoryBackend};
+
+pub const WIDTH: usize = 40;
+pub const HEIGHT: usize = 12;
+
+static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+
+/// An editor that ran a key script against a file in a fresh temporary
+/// directory, keeping the backend to inspect what was drawn.
+pub struct Session {
+    pub backend: MemoryBackend,
+    pub path: PathBuf,
+}
+
+impl Session {
+    pub fn rows(&self) -> Vec<String> {
+        self.backend.lines()
+    }
+
+    pub fn row(&self, y: usize) -> String {
+        self.rows().swap_remove(y)
+    }
+
+    pub fn status_line(&self) -> String {
+        self.row(HEIGHT - 2)
+    }
+
+    pub fn message(&self) -> String {
+        self.row(HEIGHT - 1)
+    }
+
+    pub fn cursor(&self) -> (usize, usize) {
+        self.backend.cursor()
+    }
+
+    /// What the file holds, which is the buffer if the script saved it.
+    pub fn file(&self) -> String {
+        std::fs::read_to_string(&self.path).unwrap_or_default()
+    }
+}
+
+pub fn temp_path(name: &str) -> PathBuf {
+    let number = NEXT_FILE.fetch_add(1, Ordering::Relaxed);
+    let directory =
+        std::env::temp_dir().join(format!("text-editor-test-{}-{number}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    directory.join(name)
+}
+
+/// Reads keys as typed, with `<Esc>`, `<CR>`, `<BS>`, `<Del>`, `<Tab>`,
+/// arrow keys and `<C-x>` for keys that aren't plain characters.
+pub fn parse_keys(script: &str) -> Vec<KeyEvent> {
+    let mut keys = Vec::new();
+    let mut rest = script;
+    while let Some(char) = rest.chars().next() {
+        if let Some((name, after)) = rest
+            .strip_prefix('<')
+            .and_then(|after| after.split_once('>'))
+        {
+            let mut modifiers = KeyModifiers::NONE;
+            let mut base = name;
+            for (prefix, modifier) in [
+                ("C-", KeyModifiers::CONTROL),
+                ("M-", KeyModifiers::ALT),
+                ("S-", KeyModifiers::SHIFT),
+            ] {
+                if let Some(stripped) = base.strip_prefix(prefix) {
+                    modifiers.insert(modifier);
+                    base = stripped;
+                }
+            }
+            let code = match base {
+                "Esc" => Some(KeyCode::Esc),
+                "CR" => Some(KeyCode::Enter),
+                "BS" => Some(KeyCode::Backspace),
+                "Del" => Some(KeyCode::Delete),
+                "Tab" => Some(KeyCode::Tab),
+                "Up" => Some(KeyCode::Up),
+                "Down" => Some(KeyCode::Down),
+                "Left" => Some(KeyCode::Left),
+                "Right" => Some(KeyCode::Right),
+                "Home" => Some(KeyCode::Home),
+                "End" => Some(KeyCode::End),
+                "lt" => Some(KeyCode::Char('<')),
+                _ if base.len() > 1 && base.starts_with('F') => {
+                    base[1..].parse().ok().map(KeyCode::F)
+                }
+                _ if base.chars().count() == 1 && !modifiers.is_empty() => {
+                    base.chars().next().map(KeyCode::Char)
+                }
+                _ => None,
+            };
+            let key = code.map(|code| KeyEvent::new(code, modifiers));
+            if let Some(key) = key {
+                keys.push(key);
+                rest = after;
+                continue;
+            }
+        }
+        keys.push(KeyEvent::new(KeyCode::Char(char), KeyModifiers::NONE));
+        rest = &rest[char.len_utf8()..];
+    }
+    keys
+}
+
+pub fn send(backend: &MemoryBackend, script: &str) {
+    for key in parse_keys(script) {
+        backend.push_event(Event::Key(key));
+    }
+}
+
+/// Opens `text` in an editor and types `script`.
+pub fn run(text: &str, script: &str) -> Session {
+    run_with_config(None, text, script)
+}
+
+/// Opens `text` in an editor that loaded `config` as its config file, if
+/// given, and types `script`.
+pub fn run_with_config(config: Option<&str>, text: &str, script: &str) -> Session {
+    let path = temp_path("file.txt");
+    std::fs::write(&path, text).unwrap();
+    run_file(path, config, script)
+}
+
+/// Opens the file at `path` like `run_with_config`, for scripts that need
+/// to name the file.
+pub fn run_file(path: PathBuf, config: Option<&str>, script: &str) -> Session {
+    run_file_with_width(WIDTH, path, config, script)
+}
+
+/// Opens the file at `path` like `run_file`, on a screen `width` columns
+/// wide, for messages too long to read on the usual one.
+pub fn run_file_with_width(
+    width: usize,
+    path: PathBuf,
+    config: Option<&str>,
+    script: &str,
+) -> Session {
+    let backend = MemoryBackend::new(width, HEIGHT);
+    send(&backend, script);
+    let mut editor = Editor::with_backend(path.to_str(), Box::new(backend.clone()));
+    if let Some(config) = config {
+        let config_path = path.with_file_name("config.toml");
+        std::fs::write(&config_path, config).unwrap();
+        editor.load_config(&config_path);
+    }
+    editor.run();
+    Session { backend, path }
+}
+
+/// Opens `text`, types `script`, saves and returns the file's contents.
+pub fn edit(text: &str, script: &str) -> String {
+    run(text, &format!("{script}<Esc>:w<CR>")).file()
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use common::{run_file_with_width, run_with_config, temp_path};
+
+#[test]
+fn applies_options_from_the_config_file() {
+    let config = "tabstop = 8\n\n[options]\nexpandtab = false\n";
+    let session = run_with_config(Some(config), "\tx", "$a<Tab>y<Esc>:w<CR>");
+    assert_eq!(session.row(0), "        x       y");
+    assert_eq!(session.file(), "\tx\ty");
+}
+
+#[test]
+fn maps_keys_from_keymap_tables() {
+    let config = "[keymap.control]\nQ = \"dd\"\n\n[keymap.insert]\njk = \"escape\"\n";
+    let session = run_with_config(Some(config), "a\nb", "Qixjkx:w<CR>");
+    assert_eq!(session.file(), "xb");
+}
+
+/// Checks the message shown, which is cut off at the edge of the screen.
+fn assert_message(session: &common::Session, line: usize, message: &str) {
+    let config_path = session.path.with_file_name("config.toml");
+    let expected = format!("{}:{line}: {message}", config_path.display());
+    let shown = session.message();
+    assert!(!shown.is_empty() && expected.starts_with(&shown), "{shown}");
+}
+
+#[test]
+fn reports_unknown_options_and_applies_the_rest() {
+    let config = "nosuch = 1\ntabstop = 8\n";
+    let session = run_with_config(Some(config), "\tx", "");
+    assert_message(&session, 1, "Unknown option: nosuch");
+    assert_eq!(session.row(0), "        x");
+}
+
+#[test]
+fn reports_unknown_tables() {
+    let session = run_with_config(Some("[colors]\ntabstop = 8\n"), "\tx", "");
+    assert_message(&session, 2, "Unknown table: colors");
+    assert_eq!(session.row(0), "    x");
+}
+
+/// What `:set name?` shows once `config` is loaded.
+fn option(config: &str, name: &str) -> String {
+    let session = run_with_config(Some(config), "", &format!(":set {name}?<CR>"));
+    session.message().trim().to_string()
+}
+
+/// The error `config` fails to parse with, on a screen wide enough to show
+/// it after the config file's path.
+fn parse_error(config: &str) -> String {
+    let path = temp_path("file.txt");
+    let config_path = path.with_file_name("config.toml");
+    let session = run_file_with_width(200, path, Some(config), "");
+    let message = session.message();
+    let prefix = format!("{}: ", config_path.display());
+    message
+        .trim_end()
+        .strip_prefix(&prefix)
+        .unwrap_or(&message)
+        .to_string()
+}
+
+#[test]
+fn parses_tables_and_dotted_headers() {
+    let config = "tabstop = 4\n[options]\nexpandtab = false\n[keymap.insert]\njk = \"escape\"\n[ keymap . \"control\" ] # comment\nQ = \"dd\"\n";
+    let session = run_with_config(Some(config), "a\nb", "Qix<Tab>jk:w<CR>");
+    assert_eq!(session.row(0), "x   b");
+    assert_eq!(session.file(), "x\tb");
+}
+
+#[test]
+fn parses_quoted_keys() {
+    let config =
+        "[keymap.control]\n\"<C-s>\" = \":w<CR>\"\n'Z Z' = \"dd\"\n\"Z\\\"\" = \"dd\"\nZ-_1 = \"dd\"";
+    let session = run_with_config(Some(config), "ab\ncd\nef\ngh", "Z\"Z-_1Z Z<C-s>");
+    assert_eq!(session.file(), "gh");
+}
+
+#[test]
+fn parses_string_escapes() {
+    let config = r#"[keymap.control]
+Q = "at\tr\rq\"b\\u\u00e9U\U0001F600<Esc>"
+W = '$a\n<Esc>'"#;
+    let session = run_with_config(Some(config), "", "QW:w<CR>");
+    assert_eq!(session.file(), "t\tr\rq\"b\\u\u{e9}U\u{1F600}\\n");
+    let session = run_with_config(Some("[keymap.control]\nQ = \"ia\\nb<Esc>\""), "", "Q:w<CR>");
+    assert_eq!(session.file(), "a\nb");
+}
+
+#[test]
+fn parses_integers_and_booleans() {
+    assert_eq!(
+        option("timeoutlen = 1_000", "timeoutlen"),
+        "timeoutlen=1000"
+    );
+    assert_eq!(option("scrolloff = 0", "scrolloff"), "scrolloff=0");
+    assert_eq!(option("wrap = false", "wrap"), "nowrap");
+    assert_eq!(option("wrap = false\nwrap = true", "wrap"), "wrap");
+}
+
+#[test]
+fn skips_comments_and_blank_lines() {
+    let config = "# comment\n\n   \ntabstop = 2 # trailing\n  # indented";
+    assert_eq!(option(config, "tabstop"), "tabstop=2");
+}
+
+#[test]
+fn reports_parse_errors_with_their_line_number() {
+    assert_eq!(
+        parse_error("wrap = true\n\nwrap true"),
+        "line 3: expected `=`"
+    );
+    assert_eq!(parse_error("a = yes"), "line 1: invalid value `yes`");
+    assert_eq!(parse_error("a = 1.5"), "line 1: unexpected `.5`");
+    assert_eq!(parse_error("\na = \"open"), "line 2: unterminated string");
+    assert_eq!(parse_error("a = \"\\q\""), "line 1: invalid escape");
+    assert_eq!(
+        parse_error("a = \"\\u12\""),
+        "line 1: invalid escape `\\u12\"`"
+    );
+    assert_eq!(parse_error("[options"), "line 1: expected `]`");
+    assert_eq!(parse_error("[]"), "line 1: expected a key");
+    assert_eq!(parse_error("= 1"), "line 1: expected a key");
+    assert_eq!(parse_error("a = 1 b"), "line 1: unexpected `b`");
+    assert_eq!(parse_error("a = [1]"), "line 1: invalid value ``");
+}
@@ -0,0 +1,75 @@
+mod common;
+
+use common::{edit, run, run_file, temp_path, HEIGHT};
+
+#[test]
+fn draws_the_file_and_fills_the_rest_with_tildes() {
+    let session = run("first\nsecond", "");
+    let rows = session.rows();
+    assert_eq!(rows[0], "first");
+    assert_eq!(rows[1], "second");
+    assert!(rows[2..HEIGHT - 2].iter().all(|row| row == "~"));
+    assert_eq!(session.cursor(), (0, 0));
+}
+
+#[test]
+fn moves_the_cursor_with_hjkl() {
+    let session = run("abc\ndef", "jll");
+    assert_eq!(session.cursor(), (2, 1));
+    let session = run("abc\ndef", "jllkh");
+    assert_eq!(session.cursor(), (1, 0));
+}
+
+#[test]
+fn inserts_and_deletes_text_in_insert_mode() {
+    assert_eq!(edit("world", "ihello <Esc>"), "hello world");
+    assert_eq!(edit("ab", "a<CR>x<Esc>"), "a\nxb");
+    assert_eq!(edit("ab\ncd", "ji<BS><Esc>"), "abcd");
+}
+
+#[test]
+fn counts_cursor_columns_in_graphemes_and_cells() {
+    let session = run("中e\u{301}x", "ll");
+    assert_eq!(session.cursor(), (3, 0));
+    assert_eq!(edit("中e\u{301}x", "li<Del><Esc>"), "中x");
+}
+
+#[test]
+fn undoes_and_redoes_edits() {
+    assert_eq!(edit("abc", "ix<Esc>iy<Esc>u"), "xabc");
+    assert_eq!(edit("abc", "ix<Esc>iy<Esc>uu<C-r>"), "xabc");
+}
+
+#[test]
+fn shows_the_mode_and_messages() {
+    let session = run("abc", "i");
+    assert!(session.status_line().starts_with(" INSERT"));
+    let session = run("abc", ":nonsense<CR>");
+    assert_eq!(session.message(), "Not an editor command: nonsense");
+}
+
+#[test]
+fn writes_the_file_with_w() {
+    let session = run("abc", "ix<Esc>:w<CR>");
+    assert_eq!(session.file(), "xabc");
+    assert!(!session.status_line().contains("[+]"));
+}
+
+#[test]
+fn edits_the_current_file_again_only_when_unmodified_or_forced() {
+    let open = |script: &str| {
+        let path = temp_path("file.txt");
+        std::fs::write(&path, "abc").unwrap();
+        let script = script.replace("%", path.to_str().unwrap());
+        run_file(path, None, &script)
+    };
+    let session = open("ix<Esc>:e %<CR>");
+    assert!(session.message().starts_with("No write since last change"));
+    assert_eq!(session.row(0), "xabc");
+    assert!(session.status_line().contains("[+]"));
+    let session = open("ix<Esc>:e! %<CR>");
+    assert_eq!(session.row(0), "abc");
+    assert!(!session.status_line().contains("[+]"));
+    let session = open("ix<Esc>:e!<CR>");
+    assert_eq!(session.row(0), "abc");
+}
@@ -0,0 +1,29 @@
+mod common;
+
+use common::edit;
+
+#[test]
+fn records_and_plays_a_macro() {
+    assert_eq!(edit("a\nb\nc\nd", "qaddq@a"), "c\nd");
+    assert_eq!(edit("a\nb\nc\nd", "qaddq2@a"), "d");
+    assert_eq!(edit("a\nb\nc\nd", "qaddq@a@@"), "d");
+    assert_eq!(edit("x", "qaia<Esc>q2@a"), "aaax");
+}
+
+#[test]
+fn writes_a_macro_in_key_notation() {
+    assert_eq!(edit("", "qai<lt><Esc>q\"ap"), "<i<lt><Esc>");
+    assert_eq!(
+        edit("", "qa<F5><M-x><S-Left><C-Home><C-S-Right><M-A>q\"aP"),
+        "<F5><M-x><S-Left><C-Home><C-S-Right><M-S-a>"
+    );
+}
+
+#[test]
+fn plays_a_macro_written_as_text() {
+    assert_eq!(
+        edit("ix<S-Left>y<Esc>\nz", "\"by$j@b"),
+        "ix<S-Left>y<Esc>\nyxz"
+    );
+    assert_eq!(edit("i<F5>x<Esc>\nz", "\"by$j@b"), "i<F5>x<Esc>\nxz");
+}
@@ -0,0 +1,59 @@
+mod common;
+
+use common::edit;
+
+#[test]
+fn jumps_to_marks() {
+    assert_eq!(edit("ab\ncd", "jlmagg`ai|<Esc>"), "ab\nc|d");
+    assert_eq!(edit("ab\n  cd", "jlmagg'ai|<Esc>"), "ab\n  |cd");
+}
+
+#[test]
+fn keeps_marks_on_their_lines_when_lines_change_above() {
+    assert_eq!(edit("ab\ncd", "jlmaggdd`ai|<Esc>"), "c|d");
+    assert_eq!(edit("ab\ncd", "jlmaggyyP`ai|<Esc>"), "ab\nab\nc|d");
+}
+
+#[test]
+fn moves_marks_to_the_line_a_join_keeps() {
+    assert_eq!(edit("ab\ncd", "jlma0i<BS><Esc>`ai|<Esc>"), "abc|d");
+    assert_eq!(edit("ab\ncd", "jlmak$a<Del><Esc>`ai|<Esc>"), "abc|d");
+    assert_eq!(
+        edit("ab\ncd\nef", "jjlmaggjd$i<Del><Esc>`ai|<Esc>"),
+        "ab\ne|f"
+    );
+}
+
+#[test]
+fn shifts_mark_columns_with_text_on_their_line() {
+    assert_eq!(edit("abcd", "llma0iXY<Esc>`ai|<Esc>"), "XYab|cd");
+    assert_eq!(edit("one two", "wma0dw`ai|<Esc>"), "|two");
+    assert_eq!(edit("abcdef", "llmaldw`ai|<Esc>"), "ab|c");
+    assert_eq!(edit("abcdef", "llma0d3l`ai|<Esc>"), "|def");
+    assert_eq!(edit("abcd", "lma$iXY<Esc>`ai|<Esc>"), "a|bcXYd");
+}
+
+#[test]
+fn keeps_jumps_on_their_lines_when_lines_change_above() {
+    assert_eq!(edit("a\nb\nc\nd\ne", "jjGggdd<C-o>i|<Esc>"), "b\nc\nd\n|e");
+    assert_eq!(
+        edit("a\nb\nc\nd\ne", "jjGggdd<C-o><C-o>i|<Esc>"),
+        "b\n|c\nd\ne"
+    );
+    assert_eq!(edit("a\nb\nc", "jGggyyP<C-o><C-o>i|<Esc>"), "a\na\n|b\nc");
+}
+
+#[test]
+fn keeps_only_the_last_hundred_jumps() {
+    let text = (0..110)
+        .map(|line| line.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    let jumps: String = (2..=110).map(|line| format!("{line}G")).collect();
+    let script = format!("{jumps}{}i|<Esc>", "<C-o>".repeat(120));
+    let edited = edit(&text, &script);
+    assert_eq!(
+        edited.lines().find(|line| line.starts_with("|")),
+        Some("|10")
+    );
+}
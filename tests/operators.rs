@@ -0,0 +1,59 @@
+mod common;
+
+use common::{edit, run};
+
+#[test]
+fn deletes_with_motions_and_counts() {
+    assert_eq!(edit("one two three", "dw"), "two three");
+    assert_eq!(edit("one two three", "2dw"), "three");
+    assert_eq!(edit("one two three", "d2w"), "three");
+    assert_eq!(edit("one two three", "wd$"), "one ");
+    assert_eq!(edit("a\nb\nc\nd", "dj"), "c\nd");
+    assert_eq!(edit("a\nb\nc\nd", "2dd"), "c\nd");
+    assert_eq!(edit("a(b)c", "f(d%"), "ac");
+    assert_eq!(edit("abcdef", "dfd"), "ef");
+    assert_eq!(edit("abcdef", "dtd"), "def");
+}
+
+#[test]
+fn changes_text_and_lines() {
+    assert_eq!(edit("one two", "cwnew<Esc>"), "new two");
+    assert_eq!(edit("a\nb", "ccx<Esc>"), "x\nb");
+}
+
+#[test]
+fn indents_and_dedents_lines() {
+    assert_eq!(edit("a\nb", ">j"), "    a\n    b");
+    assert_eq!(edit("    a\n\tb", "<j"), "a\nb");
+}
+
+#[test]
+fn yanks_without_changing_the_buffer() {
+    let session = run("a\nb\nc", "yj");
+    assert_eq!(session.message(), "");
+    assert_eq!(edit("a\nb\nc", "yjGp"), "a\nb\nc\na\nb");
+}
+
+#[test]
+fn cancels_a_pending_operator_with_escape() {
+    let session = run("abc", "d");
+    assert!(
+        session.status_line().starts_with(" PENDING"),
+        "{}",
+        session.status_line()
+    );
+    assert_eq!(edit("abc", "d<Esc>w"), "abc");
+}
+
+#[test]
+fn stops_word_motions_at_the_end_of_the_line() {
+    assert_eq!(edit("foo\nbar", "dw"), "\nbar");
+    assert_eq!(edit("foo\n    bar", "dw"), "\n    bar");
+    assert_eq!(edit("foo bar\nbaz", "$dw"), "foo ba\nbaz");
+    assert_eq!(edit("a b\nc d", "d2w"), "\nc d");
+    assert_eq!(edit("a\nb\nc d", "d2w"), "\nc d");
+    assert_eq!(edit("a b\nc d", "d3w"), "d");
+    assert_eq!(edit("foo.bar\nbaz", "dW"), "\nbaz");
+    assert_eq!(edit("foo\n  bar", "ywjP"), "foo\nfoo  bar");
+    assert_eq!(edit("foo\nbar", "cwx<Esc>"), "x\nbar");
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use common::edit;
+
+#[test]
+fn yanks_and_puts_through_named_registers() {
+    assert_eq!(edit("a\nb", "\"ayyj\"ap"), "a\nb\na");
+    assert_eq!(edit("a\nb", "\"ayyj\"Ayy\"aP"), "a\na\nb\nb");
+    assert_eq!(edit("one two", "\"adw$\"ap"), "twoone ");
+}
+
+#[test]
+fn keeps_the_last_yank_in_register_zero() {
+    assert_eq!(edit("a\nb\nc", "yyjddk\"0p"), "a\na\nc");
+    assert_eq!(edit("a\nb\nc", "yyjdd\"0P"), "a\na\nc");
+}
+
+#[test]
+fn shifts_line_deletes_through_numbered_registers() {
+    assert_eq!(edit("a\nb\nc", "dddd\"2p"), "c\na");
+    assert_eq!(edit("a b", "dw\"-p"), "ba ");
+}
+
+#[test]
+fn discards_text_deleted_into_the_black_hole_register() {
+    assert_eq!(edit("a\nb", "yyj\"_ddp"), "a\na");
+}
+
+#[test]
+fn reports_an_empty_register() {
+    let session = common::run("a", "\"zp");
+    assert_eq!(session.message(), "Nothing in register z");
+}
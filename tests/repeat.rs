@@ -0,0 +1,28 @@
+mod common;
+
+use common::edit;
+
+#[test]
+fn repeats_an_insert_count_times() {
+    assert_eq!(edit("x", "3iab<Esc>"), "abababx");
+    assert_eq!(edit("x", "2aa<CR><Esc>"), "xa\na\n");
+}
+
+#[test]
+fn repeats_the_last_change_with_dot() {
+    assert_eq!(edit("a\nb\nc", "ddj."), "b");
+    assert_eq!(edit("one two three", "dw."), "three");
+    assert_eq!(edit("x\ny", "iab<Esc>j."), "abx\naby");
+}
+
+#[test]
+fn repeats_an_insert_with_the_count_given_to_dot() {
+    assert_eq!(edit("x\ny", "iab<Esc>j3."), "abx\nabababy");
+    assert_eq!(edit("x\ny", "2iab<Esc>j."), "ababx\nababy");
+    assert_eq!(edit("a b c d e", "dw2."), "d e");
+}
+
+#[test]
+fn undoes_a_repeated_insert_at_once() {
+    assert_eq!(edit("x", "3iab<Esc>u"), "x");
+}
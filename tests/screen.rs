@@ -0,0 +1,51 @@
+mod common;
+
+use common::{run, WIDTH};
+
+#[test]
+fn shows_mode_file_and_position_on_the_status_line() {
+    let session = run("abc\ndef", "jl");
+    let status = session.status_line();
+    assert!(status.starts_with(" CONTROL  "), "{status}");
+    assert!(status.contains("file.txt"), "{status}");
+    assert!(status.ends_with("2:2  2L  100%"), "{status}");
+}
+
+#[test]
+fn shortens_the_file_name_to_keep_the_position_visible() {
+    let session = run("abc", "ix<Esc>");
+    let status = session.status_line();
+    assert_eq!(status.chars().count(), WIDTH - 1, "{status}");
+    assert!(status.contains("<"), "{status}");
+    assert!(status.contains("file.txt [+]"), "{status}");
+    assert!(status.ends_with("1:2  1L  100%"), "{status}");
+}
+
+#[test]
+fn draws_tabs_up_to_the_next_tab_stop() {
+    let session = run("\tx\nab\tc\nabcd\te", "");
+    assert_eq!(session.row(0), "    x");
+    assert_eq!(session.row(1), "ab  c");
+    assert_eq!(session.row(2), "abcd    e");
+    let session = run("\tx\nab\tc", ":set ts=8<CR>j$");
+    assert_eq!(session.row(0), "        x");
+    assert_eq!(session.row(1), "ab      c");
+    assert_eq!(session.cursor(), (8, 1));
+}
+
+#[test]
+fn lines_up_typed_tabs_with_drawn_tabs() {
+    let session = run("ab", ":set noet<CR>$a<Tab>x<Esc>:w<CR>");
+    assert_eq!(session.file(), "ab\tx");
+    assert_eq!(session.row(0), "ab  x");
+    let session = run("ab\ta", "$a<Tab>x<Esc>:w<CR>");
+    assert_eq!(session.file(), "ab\ta   x");
+    assert_eq!(session.row(0), "ab  a   x");
+}
+
+#[test]
+fn draws_tabs_in_wrapped_lines() {
+    let session = run("a\tb\tc", ":set wrap<CR>$");
+    assert_eq!(session.row(0), "a   b   c");
+    assert_eq!(session.cursor(), (8, 0));
+}
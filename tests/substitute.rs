@@ -0,0 +1,34 @@
+mod common;
+
+use common::{edit, run};
+
+#[test]
+fn substitutes_on_the_current_line() {
+    assert_eq!(edit("aa\naa", ":s/a/b/<CR>"), "ba\naa");
+    assert_eq!(edit("aa\naa", ":s/a/b/g<CR>"), "bb\naa");
+}
+
+#[test]
+fn substitutes_over_ranges() {
+    assert_eq!(edit("a\na\na", ":%s/a/b/<CR>"), "b\nb\nb");
+    assert_eq!(edit("a\na\na", ":2,$s/a/b/<CR>"), "a\nb\nb");
+    assert_eq!(edit("a\na\na", ":.+1s/a/b/<CR>"), "a\nb\na");
+}
+
+#[test]
+fn expands_backreferences_and_ignores_case() {
+    assert_eq!(edit("ab", ":s/(a)(b)/\\2\\1&/<CR>"), "baab");
+    assert_eq!(edit("Ab", ":s/a/x/i<CR>"), "xb");
+}
+
+#[test]
+fn reports_ranges_past_the_end_or_out_of_isize() {
+    let session = run("a\nb", ":5s/a/b/<CR>");
+    assert_eq!(session.message(), "Invalid range");
+    let session = run("a\nb", ":9223372036854775807+1s/a/b/<CR>");
+    assert_eq!(session.message(), "Invalid range");
+    let session = run("a\nb", ":.-99999999999999999999s/a/b/<CR>");
+    assert_eq!(session.message(), "Invalid range");
+    let session = run("a\nb", ":$+9223372036854775807s/a/b/<CR>");
+    assert_eq!(session.message(), "Invalid range");
+}
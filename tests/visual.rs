@@ -0,0 +1,48 @@
+mod common;
+
+use common::{edit, run};
+
+#[test]
+fn deletes_and_changes_selections() {
+    assert_eq!(edit("one two", "vlld"), " two");
+    assert_eq!(edit("a\nb\nc", "Vjd"), "c");
+    assert_eq!(edit("abc\ndef", "l<C-v>jd"), "ac\ndf");
+    assert_eq!(edit("abc\ndef", "<C-v>jIx<Esc>"), "xabc\nxdef");
+}
+
+#[test]
+fn puts_a_block_at_the_cursor_column() {
+    assert_eq!(edit("abcd\nefgh", "<C-v>jly$p"), "abcdab\nefghef");
+    assert_eq!(edit("abcd\nefgh", "<C-v>jldlP"), "cabd\ngefh");
+    assert_eq!(edit("ab\ncd", "<C-v>jy$3p"), "abaaa\ncdccc");
+}
+
+#[test]
+fn pads_short_lines_and_adds_lines_for_a_block() {
+    assert_eq!(edit("abc\nd", "<C-v>j$y0P"), "abcabc\nd  d");
+    assert_eq!(edit("ab\ncd\nx", "<C-v>jyG$p"), "ab\ncd\nxa\n c");
+}
+
+#[test]
+fn extends_the_selection_with_search() {
+    assert_eq!(edit("one two\nthree two", "v/two<CR>d"), "wo\nthree two");
+    assert_eq!(edit("one two\nthree two", "/two<CR>0vnnd"), "wo");
+    assert_eq!(edit("a\nb\nthree\nc", "GV?b<CR>d"), "a");
+}
+
+#[test]
+fn returns_to_visual_mode_after_the_search_command_line() {
+    let session = run("one two", "v/tw<Esc>");
+    assert!(
+        session.status_line().starts_with(" VISUAL"),
+        "{}",
+        session.status_line()
+    );
+    let session = run("one two", "v/tw<CR>");
+    assert!(
+        session.status_line().starts_with(" VISUAL"),
+        "{}",
+        session.status_line()
+    );
+    assert_eq!(session.cursor(), (4, 0));
+}